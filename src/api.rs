@@ -1,38 +1,79 @@
-use std::collections::HashMap;
+use std::{collections::HashMap, time::Duration};
 
+use futures::{stream, Stream, StreamExt, TryStreamExt};
+use reqwest::Url;
 use serde::{de::DeserializeOwned, Deserialize};
+use serde_json::value::RawValue;
 
-use crate::data::Spec;
-
-const TRANSITLAND_BASE_URL: &'static str = "https://transit.land/api/v2/rest";
+use crate::{
+    data::Spec,
+    error::{decode, decode_within, Error, Result},
+    query::{GeoFilter, QueryParams},
+    Client,
+};
 
 #[derive(Debug, Deserialize)]
 struct Meta {
     after: u64,
-    next: String,
+    next: Option<String>,
 }
 
 /// Trait for query-able Transitland types.
 pub trait TransitlandObject<P>: DeserializeOwned {
+    /// Whether searches for this type can be filtered by [`Spec`].
+    const ACCEPTS_SPEC: bool = false;
+    /// Whether searches for this type can be filtered by [`GeoFilter`].
+    const ACCEPTS_GEO: bool = false;
+    /// How long responses for this type are cached when the server does not
+    /// say otherwise.
+    ///
+    /// See also: [`cache`](crate::cache)
+    const CACHE_TTL: Duration = Duration::ZERO;
+
+    /// Filters available when searching for this type.
+    type Query: QueryParams;
+
     fn query_path(parent: P) -> String;
     fn by_id_path(parent: P) -> String;
 }
 
 /// A Transitland API request.
+#[derive(Debug, Clone)]
 pub struct Request {
-    spec: Spec,
+    client: Client,
+    spec: Option<Spec>,
     after: Option<u64>,
     limit: u64,
-    base_url: String,
+    max_items: Option<u64>,
+    geo: Option<GeoFilter>,
+    base_url: Option<String>,
+    revalidate: bool,
 }
 
 impl Request {
-    pub fn new() -> Self {
+    /// Create a request sent through the given client, which supplies the
+    /// API key, base URL and connection pool.
+    ///
+    /// Requests used to be created without a client and took the API key on
+    /// every call. Code such as `Request::new().search(api_key, query)` should
+    /// now share one [`Client`] and call `client.search(query)`, or use the
+    /// top-level [`search`] and [`get`] functions.
+    ///
+    /// Requests are not filtered by [`Spec`] unless
+    /// [`with_spec`](Request::with_spec) is called, so feed searches return
+    /// feeds of every spec.
+    ///
+    /// See also: [`Client::request`]
+    pub fn new(client: Client) -> Self {
         Request {
-            spec: Spec::GTFS,
+            client,
+            spec: None,
             after: None,
             limit: 20,
-            base_url: TRANSITLAND_BASE_URL.to_owned(),
+            max_items: None,
+            geo: None,
+            base_url: None,
+            revalidate: false,
         }
     }
 
@@ -40,49 +81,168 @@ impl Request {
         &self,
         parent: P,
         query: &str,
-        api_key: &str,
     ) -> Result<SearchResponse<T>> {
-        let client = reqwest::Client::new();
-        client
-            .get(format!(
-                "{}/{}",
-                TRANSITLAND_BASE_URL,
-                T::query_path(parent)
-            ))
-            .query(&[
-                ("apikey", api_key),
-                ("search", query),
-                ("limit", &self.limit.to_string()),
-            ])
-            .send()
-            .await?
-            .json()
+        self.search_filtered_with_parent(parent, query, &T::Query::default())
+            .await
+    }
+
+    /// Search for objects matching the given type-specific filters.
+    pub async fn search_filtered_with_parent<P, T: TransitlandObject<P>>(
+        &self,
+        parent: P,
+        query: &str,
+        filter: &T::Query,
+    ) -> Result<SearchResponse<T>> {
+        let params = self.search_params::<P, T>(query, filter);
+        self.search_path(T::query_path(parent), params, T::CACHE_TTL)
             .await
     }
 
+    /// Search for objects, walking through every page of results.
+    ///
+    /// Pages are fetched lazily as the stream is polled, and the stream ends
+    /// after the last page or once [`Request::with_max_items`] items have been
+    /// yielded.
+    pub fn search_stream_with_parent<P, T: TransitlandObject<P>>(
+        &self,
+        parent: P,
+        query: &str,
+    ) -> impl Stream<Item = Result<T>> {
+        self.search_stream_filtered_with_parent(parent, query, &T::Query::default())
+    }
+
+    /// Search for objects matching the given type-specific filters, walking
+    /// through every page of results.
+    ///
+    /// See also: [`Request::search_stream_with_parent`]
+    pub fn search_stream_filtered_with_parent<P, T: TransitlandObject<P>>(
+        &self,
+        parent: P,
+        query: &str,
+        filter: &T::Query,
+    ) -> impl Stream<Item = Result<T>> {
+        let path = T::query_path(parent);
+        let params = self.search_params::<P, T>(query, filter);
+        let max_items = self.max_items.unwrap_or(u64::MAX);
+
+        stream::try_unfold(Some(self.clone()), move |request| {
+            let path = path.clone();
+            let params = params.clone();
+            async move {
+                let Some(request) = request else {
+                    return Ok::<_, Error>(None);
+                };
+                let response: SearchResponse<T> =
+                    request.search_path(path, params, T::CACHE_TTL).await?;
+                let next = response.next_after().map(|after| request.with_after(after));
+                let values = response.into_values();
+                let next = if values.is_empty() { None } else { next };
+                Ok(Some((stream::iter(values.into_iter().map(Ok)), next)))
+            }
+        })
+        .try_flatten()
+        .take(max_items.try_into().unwrap_or(usize::MAX))
+    }
+
+    /// Query parameters describing a search for `T`, excluding those used for
+    /// authentication and pagination.
+    ///
+    /// Filters specific to `T` take precedence over those set on the request.
+    fn search_params<P, T: TransitlandObject<P>>(
+        &self,
+        query: &str,
+        filter: &T::Query,
+    ) -> Vec<(&'static str, String)> {
+        let mut params = vec![("search", query.to_owned())];
+        params.extend(filter.params());
+        if let Some(spec) = self.spec.filter(|_| T::ACCEPTS_SPEC) {
+            if !params.iter().any(|(name, _)| *name == "spec") {
+                params.push(("spec", spec.as_str().to_owned()));
+            }
+        }
+        if let Some(geo) = self.geo.filter(|_| T::ACCEPTS_GEO) {
+            params.extend(geo.params());
+        }
+        params
+    }
+
+    async fn search_path<T: DeserializeOwned>(
+        &self,
+        path: String,
+        params: Vec<(&'static str, String)>,
+        ttl: Duration,
+    ) -> Result<SearchResponse<T>> {
+        let mut all_params = vec![("limit", self.limit.to_string())];
+        if let Some(after) = self.after {
+            all_params.push(("after", after.to_string()));
+        }
+        all_params.extend(params.iter().cloned());
+
+        let body = self
+            .client
+            .fetch(
+                self.base_url.as_deref(),
+                &path,
+                &all_params,
+                ttl,
+                self.revalidate,
+            )
+            .await?;
+        let mut response = SearchResponse::<T>::decode(&body)?;
+        response.cursor = Some(Cursor {
+            request: self.clone(),
+            path,
+            params,
+            ttl,
+        });
+        Ok(response)
+    }
+
     pub async fn get_with_parent<P, T: TransitlandObject<P>>(
         &self,
         parent: P,
         key: &str,
-        api_key: &str,
     ) -> Result<Option<T>> {
-        let client = reqwest::Client::new();
-        client
-            .get(format!(
-                "{}/{}/{}",
-                TRANSITLAND_BASE_URL,
-                T::by_id_path(parent),
-                key
-            ))
-            .query(&[("apikey", api_key), ("limit", &self.limit.to_string())])
-            .send()
-            .await?
-            .json()
-            .await
+        let path = format!("{}/{}", T::by_id_path(parent), encode_path_segment(key));
+
+        let body = self
+            .client
+            .fetch(
+                self.base_url.as_deref(),
+                &path,
+                &[],
+                T::CACHE_TTL,
+                self.revalidate,
+            )
+            .await?;
+        let response = SearchResponse::<T>::decode(&body)?;
+        Ok(response.into_values().into_iter().next())
     }
 
+    /// Only search for feeds of the given [`Spec`]. By default, feeds of
+    /// every spec are returned.
+    ///
+    /// This has no effect on searches for types other than
+    /// [`Feed`](crate::data::Feed), and is overridden by
+    /// [`FeedQuery::spec`](crate::query::FeedQuery::spec).
     pub fn with_spec(mut self, spec: Spec) -> Self {
-        self.spec = spec;
+        self.spec = Some(spec);
+        self
+    }
+
+    /// Only search for objects within the given area.
+    ///
+    /// This has no effect on searches for types which cannot be filtered
+    /// geographically, such as [`Feed`](crate::data::Feed).
+    pub fn with_geo(mut self, geo: GeoFilter) -> Self {
+        self.geo = Some(geo);
+        self
+    }
+
+    /// Start the search after the given cursor, as returned by a previous
+    /// page of results.
+    pub fn with_after(mut self, after: u64) -> Self {
+        self.after = Some(after);
         self
     }
 
@@ -91,27 +251,66 @@ impl Request {
         self
     }
 
+    /// Limit the total number of items yielded by
+    /// [`Request::search_stream`].
+    pub fn with_max_items(mut self, max_items: u64) -> Self {
+        self.max_items = Some(max_items);
+        self
+    }
+
+    /// Check with the server that cached responses are up to date, even if
+    /// they have not yet expired.
+    pub fn with_revalidation(mut self, revalidate: bool) -> Self {
+        self.revalidate = revalidate;
+        self
+    }
+
+    /// Send this request to a different base URL than the one configured on
+    /// its [`Client`].
     pub fn with_base_url(mut self, base_url: impl Into<String>) -> Self {
-        self.base_url = base_url.into();
+        self.base_url = Some(base_url.into());
         self
     }
+
+    pub(crate) fn client(&self) -> &Client {
+        &self.client
+    }
+
+    pub(crate) fn base_url(&self) -> Option<&str> {
+        self.base_url.as_deref()
+    }
 }
 
 impl Request {
-    pub async fn search<T: TransitlandObject<()>>(
+    pub async fn search<T: TransitlandObject<()>>(&self, query: &str) -> Result<SearchResponse<T>> {
+        self.search_with_parent((), query).await
+    }
+
+    pub async fn search_filtered<T: TransitlandObject<()>>(
         &self,
         query: &str,
-        api_key: &str,
+        filter: &T::Query,
     ) -> Result<SearchResponse<T>> {
-        self.search_with_parent((), query, api_key).await
+        self.search_filtered_with_parent((), query, filter).await
     }
 
-    pub async fn get<T: TransitlandObject<()>>(
+    pub fn search_stream<T: TransitlandObject<()>>(
         &self,
-        key: &str,
-        api_key: &str,
-    ) -> Result<Option<T>> {
-        self.get_with_parent((), key, api_key).await
+        query: &str,
+    ) -> impl Stream<Item = Result<T>> {
+        self.search_stream_with_parent((), query)
+    }
+
+    pub fn search_stream_filtered<T: TransitlandObject<()>>(
+        &self,
+        query: &str,
+        filter: &T::Query,
+    ) -> impl Stream<Item = Result<T>> {
+        self.search_stream_filtered_with_parent((), query, filter)
+    }
+
+    pub async fn get<T: TransitlandObject<()>>(&self, key: &str) -> Result<Option<T>> {
+        self.get_with_parent((), key).await
     }
 }
 
@@ -122,29 +321,350 @@ pub struct SearchResponse<T: DeserializeOwned> {
     #[serde(flatten)]
     #[serde(bound = "")] // hack: https://github.com/serde-rs/serde/issues/1296
     rest: HashMap<String, Vec<T>>,
+    #[serde(skip)]
+    cursor: Option<Cursor>,
+}
+
+/// The request that produced a [`SearchResponse`], used to fetch the pages
+/// following it.
+#[derive(Debug)]
+struct Cursor {
+    request: Request,
+    path: String,
+    params: Vec<(&'static str, String)>,
+    ttl: Duration,
 }
 
 impl<T: DeserializeOwned> SearchResponse<T> {
+    /// Deserialize a response body.
+    ///
+    /// Unlike deserializing through [`Deserialize`], errors report the path
+    /// of the offending value within the body, such as `stops[3].geometry`.
+    pub(crate) fn decode(body: &[u8]) -> Result<Self> {
+        let fields: HashMap<String, &RawValue> = decode(body)?;
+        let mut response = SearchResponse {
+            meta: None,
+            rest: HashMap::new(),
+            cursor: None,
+        };
+        for (key, value) in fields {
+            let value = value.get().as_bytes();
+            if key == "meta" {
+                response.meta = decode_within(body, value, &key)?;
+            } else {
+                let values = decode_within(body, value, &key)?;
+                response.rest.insert(key, values);
+            }
+        }
+        Ok(response)
+    }
+
     pub fn values(&self) -> Option<&Vec<T>> {
         self.rest.values().last()
     }
 
-    pub async fn search_next(&self) -> Result<SearchResponse<T>> {
-        unimplemented!()
+    /// Consume the response, returning the values on this page.
+    pub fn into_values(self) -> Vec<T> {
+        self.rest.into_values().last().unwrap_or_default()
+    }
+
+    /// Whether there are further pages of results after this one.
+    pub fn has_next(&self) -> bool {
+        self.next_after().is_some()
+    }
+
+    fn next_after(&self) -> Option<u64> {
+        match &self.meta {
+            Some(Meta {
+                after,
+                next: Some(_),
+            }) => Some(*after),
+            _ => None,
+        }
+    }
+
+    /// Fetch the next page of results, or `None` if this is the last page.
+    ///
+    /// The next page is requested with the same parameters (limit, spec, base
+    /// URL and client) as the request which produced this response.
+    pub async fn search_next(&self) -> Result<Option<SearchResponse<T>>> {
+        let (Some(after), Some(cursor)) = (self.next_after(), &self.cursor) else {
+            return Ok(None);
+        };
+
+        let request = cursor.request.clone().with_after(after);
+        request
+            .search_path(cursor.path.clone(), cursor.params.clone(), cursor.ttl)
+            .await
+            .map(Some)
     }
 }
 
-pub type Result<T> = std::result::Result<T, reqwest::Error>;
+/// Percent-encode `segment` so that it stays a single path segment, even if
+/// it contains characters such as `/`, `?` or `#`.
+pub(crate) fn encode_path_segment(segment: &str) -> String {
+    let mut url = Url::parse("http://localhost").expect("valid URL");
+    url.path_segments_mut()
+        .expect("URL has a path")
+        .push(segment);
+    url.path()[1..].to_owned()
+}
 
-/// Top-level convenience wrapper for [`Request::search`].
+/// Top-level convenience wrapper for [`Client::search`].
+///
+/// Calls with the same API key share a client, and so its connection pool
+/// and cache.
 pub async fn search<T: TransitlandObject<()>>(
     api_key: &str,
     query: &str,
 ) -> Result<SearchResponse<T>> {
-    Request::new().search(api_key, query).await
+    Client::shared(api_key).search(query).await
 }
 
-/// Top-level convenience wrapper for [`Request::get`].
+/// Top-level convenience wrapper for [`Client::get`].
+///
+/// Calls with the same API key share a client, like [`search`].
 pub async fn get<T: TransitlandObject<()>>(api_key: &str, key: &str) -> Result<Option<T>> {
-    Request::new().get(api_key, key).await
+    Client::shared(api_key).get(key).await
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::{
+        query::{AgencyQuery, FeedQuery},
+        test_server::{response, test_client, TestServer},
+    };
+
+    #[derive(Debug, PartialEq, Deserialize)]
+    struct Item {
+        id: u64,
+    }
+
+    impl TransitlandObject<()> for Item {
+        const ACCEPTS_SPEC: bool = true;
+
+        type Query = FeedQuery;
+
+        fn query_path(_parent: ()) -> String {
+            "items".to_owned()
+        }
+
+        fn by_id_path(_parent: ()) -> String {
+            "items".to_owned()
+        }
+    }
+
+    /// An object which cannot be filtered by spec.
+    #[derive(Debug, PartialEq, Deserialize)]
+    struct Other {
+        id: u64,
+    }
+
+    impl TransitlandObject<()> for Other {
+        type Query = AgencyQuery;
+
+        fn query_path(_parent: ()) -> String {
+            "others".to_owned()
+        }
+
+        fn by_id_path(_parent: ()) -> String {
+            "others".to_owned()
+        }
+    }
+
+    /// A page of items with the given IDs, followed by another page starting
+    /// after `after` if given.
+    fn page(ids: &[u64], after: Option<u64>) -> Vec<u8> {
+        let items: Vec<_> = ids.iter().map(|id| json!({ "id": id })).collect();
+        let body = match after {
+            Some(after) => json!({
+                "items": items,
+                "meta": {
+                    "after": after,
+                    "next": format!("https://transit.land/api/v2/rest/items?after={}", after)
+                }
+            }),
+            None => json!({ "items": items }),
+        };
+        response(
+            200,
+            &[("Content-Type", "application/json")],
+            body.to_string(),
+        )
+    }
+
+    fn items(ids: &[u64]) -> Vec<Item> {
+        ids.iter().map(|&id| Item { id }).collect()
+    }
+
+    #[tokio::test]
+    async fn search_next() {
+        let server = TestServer::start(vec![page(&[1, 2], Some(2)), page(&[3], None)]).await;
+        let request = test_client(server.url()).request().with_limit(2);
+
+        let first: SearchResponse<Item> = request.search("bart").await.unwrap();
+        assert!(first.has_next());
+        assert_eq!(first.values(), Some(&items(&[1, 2])));
+
+        let second = first.search_next().await.unwrap().unwrap();
+        assert!(!second.has_next());
+        assert_eq!(second.values(), Some(&items(&[3])));
+        assert!(second.search_next().await.unwrap().is_none());
+
+        let requests = server.requests();
+        assert_eq!(requests.len(), 2);
+        assert!(requests[0].starts_with("get /items?apikey=secret&limit=2&search=bart "));
+        assert!(requests[1].starts_with("get /items?apikey=secret&limit=2&after=2&search=bart "));
+    }
+
+    async fn collect(request: &Request) -> Vec<Result<Item>> {
+        request.search_stream("bart").collect().await
+    }
+
+    fn ok_ids(items: Vec<Result<Item>>) -> Vec<u64> {
+        items.into_iter().map(|item| item.unwrap().id).collect()
+    }
+
+    #[tokio::test]
+    async fn search_stream() {
+        let server = TestServer::start(vec![
+            page(&[1, 2], Some(2)),
+            page(&[3, 4], Some(4)),
+            page(&[5], None),
+        ])
+        .await;
+        let request = test_client(server.url()).request().with_limit(2);
+
+        assert_eq!(ok_ids(collect(&request).await), [1, 2, 3, 4, 5]);
+        let requests = server.requests();
+        assert_eq!(requests.len(), 3);
+        assert!(requests[1].contains("&after=2&"));
+        assert!(requests[2].contains("&after=4&"));
+    }
+
+    #[tokio::test]
+    async fn search_stream_max_items() {
+        let server = TestServer::start(vec![
+            page(&[1, 2], Some(2)),
+            page(&[3, 4], Some(4)),
+            page(&[5], None),
+        ])
+        .await;
+        let request = test_client(server.url())
+            .request()
+            .with_limit(2)
+            .with_max_items(3);
+
+        assert_eq!(ok_ids(collect(&request).await), [1, 2, 3]);
+        // the page after the last item is never requested
+        assert_eq!(server.requests().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn search_stream_empty_page() {
+        // a next page is advertised but has no items
+        let server = TestServer::start(vec![page(&[1, 2], Some(2)), page(&[], Some(2))]).await;
+        let request = test_client(server.url()).request().with_limit(2);
+
+        assert_eq!(ok_ids(collect(&request).await), [1, 2]);
+        assert_eq!(server.requests().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn search_stream_error() {
+        let server = TestServer::start(vec![
+            page(&[1, 2], Some(2)),
+            response(500, &[], "{\"error\": \"internal\"}"),
+        ])
+        .await;
+        let request = test_client(server.url()).request().with_limit(2);
+
+        let mut items = collect(&request).await;
+        assert_eq!(items.len(), 3);
+        assert!(matches!(items.pop(), Some(Err(Error::Server { .. }))));
+        assert_eq!(ok_ids(items), [1, 2]);
+    }
+
+    #[tokio::test]
+    async fn base_url() {
+        let server = TestServer::start(vec![
+            page(&[1], Some(1)),
+            page(&[2], None),
+            page(&[3], None),
+        ])
+        .await;
+        // nothing listens on the client's own base URL
+        let client = test_client(TestServer::start(vec![]).await.url());
+        let request = client.request().with_base_url(format!("{}/", server.url()));
+
+        let first: SearchResponse<Item> = request.search("bart").await.unwrap();
+        // later pages are fetched from the same base URL
+        let second = first.search_next().await.unwrap().unwrap();
+        assert_eq!(second.values(), Some(&items(&[2])));
+        assert_eq!(
+            request.get::<Item>("3").await.unwrap(),
+            Some(Item { id: 3 })
+        );
+
+        let requests = server.requests();
+        assert_eq!(requests.len(), 3);
+        assert!(requests[0].starts_with("get /items?"));
+        assert!(requests[1].starts_with("get /items?"));
+        assert!(requests[2].starts_with("get /items/3?apikey=secret "));
+    }
+
+    #[tokio::test]
+    async fn get_encodes_key() {
+        let server = TestServer::start(vec![page(&[4], None)]).await;
+        let request = test_client(server.url()).request().with_limit(2);
+
+        let item: Option<Item> = request.get("s-9q9?x/y").await.unwrap();
+        assert_eq!(item, Some(Item { id: 4 }));
+        // the key stays a single path segment, and no limit is sent
+        let requests = server.requests();
+        assert!(requests[0].starts_with("get /items/s-9q9%3fx%2fy?apikey=secret "));
+    }
+
+    #[tokio::test]
+    async fn spec() {
+        let server = TestServer::start(vec![page(&[], None); 3]).await;
+        let request = test_client(server.url()).request();
+
+        request.search::<Item>("bart").await.unwrap();
+        let with_spec = request.clone().with_spec(Spec::GTFSRealtime);
+        with_spec.search::<Item>("bart").await.unwrap();
+        with_spec.search::<Other>("bart").await.unwrap();
+
+        let requests = server.requests();
+        assert!(!requests[0].contains("spec="));
+        assert!(
+            requests[1].starts_with("get /items?apikey=secret&limit=20&search=bart&spec=gtfs-rt ")
+        );
+        // only sent for types which accept it
+        assert!(requests[2].starts_with("get /others?apikey=secret&limit=20&search=bart "));
+    }
+
+    #[tokio::test]
+    async fn spec_filter_takes_precedence() {
+        let server = TestServer::start(vec![page(&[], None)]).await;
+        let request = test_client(server.url())
+            .request()
+            .with_spec(Spec::GTFSRealtime);
+
+        let filter = FeedQuery {
+            spec: Some(Spec::GBFS),
+            ..Default::default()
+        };
+        request
+            .search_filtered::<Item>("bart", &filter)
+            .await
+            .unwrap();
+
+        let requests = server.requests();
+        assert!(requests[0].contains("&spec=gbfs "));
+        assert_eq!(requests[0].matches("spec=").count(), 1);
+    }
 }
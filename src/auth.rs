@@ -0,0 +1,175 @@
+//! Authorization for protected feed URLs.
+//!
+//! Many agencies only serve their feeds to registered users, who are issued a
+//! secret such as an API key. A feed's [`Authorization`] describes how that
+//! secret is included in requests, while the secrets themselves are looked up
+//! from a [`SecretStore`] supplied by the user.
+//!
+//! See also: [`Client::feed_request`], [`Client::fetch_feed_url`]
+
+use std::collections::HashMap;
+
+use bytes::Bytes;
+use reqwest::{
+    header::{HeaderName, HeaderValue},
+    RequestBuilder,
+};
+
+use crate::{
+    api::encode_path_segment,
+    data::{Authorization, AuthorizationType, Feed},
+    Client, Error, Result,
+};
+
+/// A secret used to access a protected feed.
+#[derive(Clone, PartialEq, Eq)]
+pub enum Secret {
+    /// A key, such as an API key or token, for feeds authorized by
+    /// [`Header`](AuthorizationType::Header),
+    /// [`QueryParam`](AuthorizationType::QueryParam) or
+    /// [`PathSegment`](AuthorizationType::PathSegment).
+    Key(String),
+    /// Credentials for feeds authorized by
+    /// [`BasicAuth`](AuthorizationType::BasicAuth).
+    Basic { username: String, password: String },
+}
+
+impl std::fmt::Debug for Secret {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        // never print the secret itself
+        match self {
+            Secret::Key(_) => f.write_str("Key(..)"),
+            Secret::Basic { username, .. } => f
+                .debug_struct("Basic")
+                .field("username", username)
+                .finish_non_exhaustive(),
+        }
+    }
+}
+
+impl From<&str> for Secret {
+    fn from(key: &str) -> Self {
+        Secret::Key(key.to_owned())
+    }
+}
+
+impl From<String> for Secret {
+    fn from(key: String) -> Self {
+        Secret::Key(key)
+    }
+}
+
+/// Source of the secrets used to access protected feeds.
+pub trait SecretStore {
+    /// Look up the secret for a feed, if there is one.
+    fn secret(&self, feed: &Feed) -> Option<Secret>;
+}
+
+/// Secrets keyed by the OnestopID of their feed.
+impl SecretStore for HashMap<String, Secret> {
+    fn secret(&self, feed: &Feed) -> Option<Secret> {
+        self.get(&feed.onestop_id).cloned()
+    }
+}
+
+/// A store without any secrets, for accessing only unprotected feeds.
+impl SecretStore for () {
+    fn secret(&self, _feed: &Feed) -> Option<Secret> {
+        None
+    }
+}
+
+impl Authorization {
+    /// Build a GET request for `url`, inserting `secret` as described by this
+    /// authorization.
+    ///
+    /// Fails with [`Error::InvalidArgument`] if a secret is required but
+    /// missing or of the wrong kind.
+    pub fn apply(
+        &self,
+        http: &reqwest::Client,
+        url: &str,
+        secret: Option<&Secret>,
+    ) -> Result<RequestBuilder> {
+        let param_name = || {
+            self.param_name.as_deref().ok_or_else(|| {
+                Error::InvalidArgument("feed authorization is missing a parameter name".into())
+            })
+        };
+
+        let auth_type = self.auth_type.unwrap_or(AuthorizationType::None);
+        Ok(match (auth_type, secret) {
+            (AuthorizationType::None, _) => http.get(url),
+            (_, None) => {
+                return Err(Error::InvalidArgument(
+                    "feed requires a secret for authorization".into(),
+                ))
+            }
+            (AuthorizationType::BasicAuth, Some(Secret::Basic { username, password })) => {
+                http.get(url).basic_auth(username, Some(password))
+            }
+            (AuthorizationType::BasicAuth, Some(Secret::Key(_))) => {
+                return Err(Error::InvalidArgument(
+                    "feed requires a username and password for authorization".into(),
+                ))
+            }
+            (_, Some(Secret::Basic { .. })) => {
+                return Err(Error::InvalidArgument(
+                    "feed requires a key for authorization".into(),
+                ))
+            }
+            (AuthorizationType::Header, Some(Secret::Key(key))) => {
+                let name = HeaderName::try_from(param_name()?)
+                    .map_err(|err| Error::InvalidArgument(err.to_string()))?;
+                let mut value = HeaderValue::try_from(key.as_str())
+                    .map_err(|err| Error::InvalidArgument(err.to_string()))?;
+                value.set_sensitive(true);
+                http.get(url).header(name, value)
+            }
+            (AuthorizationType::QueryParam, Some(Secret::Key(key))) => {
+                http.get(url).query(&[(param_name()?, key)])
+            }
+            (AuthorizationType::PathSegment, Some(Secret::Key(key))) => {
+                if !url.contains("{}") {
+                    return Err(Error::InvalidArgument(
+                        "URL has no `{}` segment to insert the secret into".into(),
+                    ));
+                }
+                http.get(url.replace("{}", &encode_path_segment(key)))
+            }
+        })
+    }
+}
+
+impl Client {
+    /// Build a GET request for one of a feed's URLs, authorized with the
+    /// feed's secret from `secrets`.
+    ///
+    /// The request is sent directly to the URL, without the Transitland API
+    /// key.
+    pub fn feed_request(
+        &self,
+        feed: &Feed,
+        url: &str,
+        secrets: &impl SecretStore,
+    ) -> Result<RequestBuilder> {
+        let secret = secrets.secret(feed);
+        feed.authorization.apply(self.http(), url, secret.as_ref())
+    }
+
+    /// Fetch one of a feed's URLs, authorized with the feed's secret from
+    /// `secrets`.
+    ///
+    /// Failed requests are retried according to the client's
+    /// [`RetryPolicy`](crate::RetryPolicy).
+    pub async fn fetch_feed_url(
+        &self,
+        feed: &Feed,
+        url: &str,
+        secrets: &impl SecretStore,
+    ) -> Result<Bytes> {
+        let secret = secrets.secret(feed);
+        self.fetch_external(|http| feed.authorization.apply(http, url, secret.as_ref()))
+            .await
+    }
+}
@@ -0,0 +1,602 @@
+//! Caching of API responses.
+//!
+//! Clients cache responses in a [`MemoryCache`] by default, which can be
+//! replaced with [`ClientBuilder::with_cache`](crate::ClientBuilder::with_cache)
+//! or disabled with
+//! [`ClientBuilder::without_cache`](crate::ClientBuilder::without_cache).
+//! Responses are stored under their URL and reused until they expire. Expired
+//! responses with an `ETag` or `Last-Modified` header are revalidated with a
+//! conditional request rather than downloaded again.
+//!
+//! Responses expire according to their `Cache-Control` header, falling back to
+//! the [`CACHE_TTL`](crate::TransitlandObject::CACHE_TTL) of the requested
+//! type. Clients built with
+//! [`ClientBuilder::with_offline_fallback`](crate::ClientBuilder::with_offline_fallback)
+//! use expired responses when the API cannot be reached, so that a persistent
+//! cache such as [`DiskCache`] allows working offline.
+
+use std::{
+    fmt,
+    fs::{self, File},
+    io::{self, BufRead, BufReader, Read, Write},
+    num::NonZeroUsize,
+    path::{Path, PathBuf},
+    sync::Mutex,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use bytes::Bytes;
+use lru::LruCache;
+use reqwest::header::{HeaderMap, CACHE_CONTROL, ETAG, LAST_MODIFIED};
+use serde::{Deserialize, Serialize};
+use sha1::{Digest, Sha1};
+
+/// Default number of responses held by a [`MemoryCache`].
+const DEFAULT_CAPACITY: usize = 1024;
+
+/// Default total size of the responses held by a [`DiskCache`], in bytes.
+const DEFAULT_MAX_SIZE: u64 = 256 * 1024 * 1024;
+
+/// Storage for API responses.
+///
+/// Implementations must be safe to share between threads, as a cache is shared
+/// by all clones of a [`Client`](crate::Client).
+pub trait Cache: Send + Sync + fmt::Debug {
+    /// Look up a response, whether or not it is still fresh.
+    fn get(&self, key: &str) -> Option<CachedResponse>;
+    /// Store a response, replacing any existing one with the same key.
+    fn put(&self, key: &str, response: CachedResponse);
+    /// Remove a response, if present.
+    fn remove(&self, key: &str);
+}
+
+/// A response stored in a [`Cache`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CachedResponse {
+    /// Body of the response.
+    pub body: Bytes,
+    /// Value of the `ETag` header, if present.
+    pub etag: Option<String>,
+    /// Value of the `Last-Modified` header, if present.
+    pub last_modified: Option<String>,
+    /// Time after which the response must be revalidated before use.
+    pub expires: SystemTime,
+}
+
+impl CachedResponse {
+    /// Build a cache entry for a response, or `None` if it should not be
+    /// stored.
+    pub(crate) fn new(body: Bytes, headers: &HeaderMap, default_ttl: Duration) -> Option<Self> {
+        let ttl = match CacheControl::parse(headers) {
+            CacheControl::NoStore => return None,
+            CacheControl::MaxAge(ttl) => ttl,
+            CacheControl::Default => default_ttl,
+        };
+        let etag = header(headers, ETAG);
+        let last_modified = header(headers, LAST_MODIFIED);
+        if ttl.is_zero() && etag.is_none() && last_modified.is_none() {
+            // would never be usable without revalidation, which isn't possible
+            return None;
+        }
+        // a TTL too long to represent is not trusted
+        let expires = SystemTime::now().checked_add(ttl)?;
+
+        Some(CachedResponse {
+            body,
+            etag,
+            last_modified,
+            expires,
+        })
+    }
+
+    /// Whether the response may be used without revalidating it.
+    pub fn is_fresh(&self) -> bool {
+        SystemTime::now() < self.expires
+    }
+
+    /// Update the response after it was revalidated by a `304 Not Modified`
+    /// response with the given headers. Returns `false` if it should no
+    /// longer be stored.
+    pub(crate) fn revalidate(&mut self, headers: &HeaderMap, default_ttl: Duration) -> bool {
+        let ttl = match CacheControl::parse(headers) {
+            CacheControl::NoStore => return false,
+            CacheControl::MaxAge(ttl) => ttl,
+            CacheControl::Default => default_ttl,
+        };
+        let Some(expires) = SystemTime::now().checked_add(ttl) else {
+            return false;
+        };
+        if let Some(etag) = header(headers, ETAG) {
+            self.etag = Some(etag);
+        }
+        if let Some(last_modified) = header(headers, LAST_MODIFIED) {
+            self.last_modified = Some(last_modified);
+        }
+        self.expires = expires;
+        true
+    }
+
+    /// Whether the response can be revalidated with a conditional request.
+    pub(crate) fn can_revalidate(&self) -> bool {
+        self.etag.is_some() || self.last_modified.is_some()
+    }
+}
+
+/// An in-memory cache, evicting the least recently used responses once full.
+#[derive(Debug)]
+pub struct MemoryCache {
+    entries: Mutex<LruCache<String, CachedResponse>>,
+}
+
+impl MemoryCache {
+    /// Create a cache holding at most `capacity` responses.
+    pub fn new(capacity: NonZeroUsize) -> Self {
+        MemoryCache {
+            entries: Mutex::new(LruCache::new(capacity)),
+        }
+    }
+}
+
+impl Default for MemoryCache {
+    fn default() -> Self {
+        MemoryCache::new(NonZeroUsize::new(DEFAULT_CAPACITY).unwrap())
+    }
+}
+
+impl Cache for MemoryCache {
+    fn get(&self, key: &str) -> Option<CachedResponse> {
+        self.entries.lock().unwrap().get(key).cloned()
+    }
+
+    fn put(&self, key: &str, response: CachedResponse) {
+        self.entries.lock().unwrap().put(key.to_owned(), response);
+    }
+
+    fn remove(&self, key: &str) {
+        self.entries.lock().unwrap().pop(key);
+    }
+}
+
+/// A cache storing each response in its own file under a directory.
+///
+/// Responses persist across runs, so that tools which repeatedly make the same
+/// requests need not download them again, and can work offline from
+/// previously fetched data (see
+/// [`ClientBuilder::with_offline_fallback`](crate::ClientBuilder::with_offline_fallback)).
+/// Once the total size of the stored responses
+/// exceeds [`DiskCache::with_max_size`], the least recently used responses are
+/// evicted.
+///
+/// Errors reading from or writing to the directory are ignored, treating the
+/// affected responses as missing.
+#[derive(Debug)]
+pub struct DiskCache {
+    dir: PathBuf,
+    max_size: u64,
+    // serializes writes and evictions by this process
+    lock: Mutex<()>,
+}
+
+/// Metadata stored on the first line of each [`DiskCache`] file, before the
+/// response body.
+#[derive(Serialize, Deserialize)]
+struct DiskMetadata {
+    key: String,
+    etag: Option<String>,
+    last_modified: Option<String>,
+    /// Seconds since the Unix epoch.
+    expires: u64,
+}
+
+impl DiskCache {
+    /// Use the given directory for the cache, creating it if needed.
+    pub fn new(dir: impl Into<PathBuf>) -> io::Result<Self> {
+        let dir = dir.into();
+        fs::create_dir_all(&dir)?;
+        Ok(DiskCache {
+            dir,
+            max_size: DEFAULT_MAX_SIZE,
+            lock: Mutex::new(()),
+        })
+    }
+
+    /// Limit the total size of the stored responses, in bytes.
+    pub fn with_max_size(mut self, max_size: u64) -> Self {
+        self.max_size = max_size;
+        self
+    }
+
+    /// Directory the responses are stored in.
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    fn path(&self, key: &str) -> PathBuf {
+        let hash = Sha1::digest(key.as_bytes());
+        self.dir.join(format!("{}.cache", hex::encode(hash)))
+    }
+
+    fn read(&self, key: &str) -> io::Result<Option<CachedResponse>> {
+        let path = self.path(key);
+        let file = match File::open(&path) {
+            Ok(file) => file,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(err) => return Err(err),
+        };
+        let mut reader = BufReader::new(file);
+
+        let mut line = String::new();
+        reader.read_line(&mut line)?;
+        let metadata: DiskMetadata = serde_json::from_str(&line)?;
+        if metadata.key != key {
+            return Ok(None);
+        }
+        let mut body = vec![];
+        reader.read_to_end(&mut body)?;
+
+        // mark as recently used for eviction, which may fail if the directory
+        // is read-only without affecting the response itself
+        let _ = File::options()
+            .append(true)
+            .open(&path)
+            .and_then(|file| file.set_modified(SystemTime::now()));
+
+        Ok(Some(CachedResponse {
+            body: body.into(),
+            etag: metadata.etag,
+            last_modified: metadata.last_modified,
+            expires: UNIX_EPOCH + Duration::from_secs(metadata.expires),
+        }))
+    }
+
+    fn write(&self, key: &str, response: &CachedResponse) -> io::Result<()> {
+        let metadata = DiskMetadata {
+            key: key.to_owned(),
+            etag: response.etag.clone(),
+            last_modified: response.last_modified.clone(),
+            expires: response
+                .expires
+                .duration_since(UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_secs(),
+        };
+
+        // write to a temporary file first, so that readers never see a
+        // partially written response
+        let path = self.path(key);
+        let tmp_path = path.with_extension("tmp");
+        let mut file = File::create(&tmp_path)?;
+        serde_json::to_writer(&mut file, &metadata)?;
+        file.write_all(b"\n")?;
+        file.write_all(&response.body)?;
+        file.sync_all()?;
+        fs::rename(tmp_path, path)
+    }
+
+    /// Remove the least recently used responses until the cache fits within
+    /// its maximum size.
+    fn evict(&self) -> io::Result<()> {
+        let mut files = vec![];
+        for entry in fs::read_dir(&self.dir)? {
+            let entry = entry?;
+            let path = entry.path();
+            if path.extension().is_some_and(|ext| ext == "cache") {
+                let metadata = entry.metadata()?;
+                files.push((metadata.modified()?, metadata.len(), path));
+            }
+        }
+
+        let mut size: u64 = files.iter().map(|(_, len, _)| len).sum();
+        files.sort();
+        for (_, len, path) in files {
+            if size <= self.max_size {
+                break;
+            }
+            fs::remove_file(path)?;
+            size -= len;
+        }
+        Ok(())
+    }
+}
+
+impl Cache for DiskCache {
+    fn get(&self, key: &str) -> Option<CachedResponse> {
+        self.read(key).ok().flatten()
+    }
+
+    fn put(&self, key: &str, response: CachedResponse) {
+        let _lock = self.lock.lock().unwrap();
+        if self.write(key, &response).is_ok() {
+            let _ = self.evict();
+        }
+    }
+
+    fn remove(&self, key: &str) {
+        let _lock = self.lock.lock().unwrap();
+        let _ = fs::remove_file(self.path(key));
+    }
+}
+
+/// The parts of a `Cache-Control` header relevant to a private cache.
+#[derive(Debug, PartialEq, Eq)]
+enum CacheControl {
+    NoStore,
+    MaxAge(Duration),
+    Default,
+}
+
+impl CacheControl {
+    fn parse(headers: &HeaderMap) -> Self {
+        let mut cache_control = CacheControl::Default;
+        let directives = headers
+            .get_all(CACHE_CONTROL)
+            .iter()
+            .filter_map(|value| value.to_str().ok())
+            .flat_map(|value| value.split(','))
+            .map(|directive| directive.trim().to_ascii_lowercase());
+
+        for directive in directives {
+            if directive == "no-store" {
+                return CacheControl::NoStore;
+            } else if directive == "no-cache" {
+                cache_control = CacheControl::MaxAge(Duration::ZERO);
+            } else if let Some(max_age) = directive.strip_prefix("max-age=") {
+                if let Ok(secs) = max_age.trim_matches('"').parse() {
+                    if !matches!(cache_control, CacheControl::MaxAge(Duration::ZERO)) {
+                        cache_control = CacheControl::MaxAge(Duration::from_secs(secs));
+                    }
+                }
+            }
+        }
+        cache_control
+    }
+}
+
+fn header(headers: &HeaderMap, name: reqwest::header::HeaderName) -> Option<String> {
+    headers
+        .get(name)
+        .and_then(|value| value.to_str().ok())
+        .map(str::to_owned)
+}
+
+#[cfg(test)]
+mod tests {
+    use reqwest::header::HeaderValue;
+
+    use super::*;
+
+    const HOUR: Duration = Duration::from_secs(60 * 60);
+
+    fn headers(pairs: &[(reqwest::header::HeaderName, &str)]) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        for (name, value) in pairs {
+            headers.append(name, HeaderValue::from_str(value).unwrap());
+        }
+        headers
+    }
+
+    fn cache_control(value: &str) -> CacheControl {
+        CacheControl::parse(&headers(&[(CACHE_CONTROL, value)]))
+    }
+
+    #[test]
+    fn parse_cache_control() {
+        assert_eq!(
+            CacheControl::parse(&HeaderMap::new()),
+            CacheControl::Default
+        );
+        assert_eq!(cache_control("public"), CacheControl::Default);
+        assert_eq!(
+            cache_control("public, max-age=60"),
+            CacheControl::MaxAge(Duration::from_secs(60))
+        );
+        assert_eq!(
+            cache_control("Max-Age=\"60\""),
+            CacheControl::MaxAge(Duration::from_secs(60))
+        );
+        assert_eq!(cache_control("max-age=soon"), CacheControl::Default);
+        assert_eq!(cache_control("max-age=60, no-store"), CacheControl::NoStore);
+        assert_eq!(cache_control("NO-STORE"), CacheControl::NoStore);
+
+        // no-cache requires revalidation, regardless of max-age
+        assert_eq!(
+            cache_control("no-cache"),
+            CacheControl::MaxAge(Duration::ZERO)
+        );
+        assert_eq!(
+            cache_control("no-cache, max-age=60"),
+            CacheControl::MaxAge(Duration::ZERO)
+        );
+        assert_eq!(
+            cache_control("max-age=60, no-cache"),
+            CacheControl::MaxAge(Duration::ZERO)
+        );
+
+        // directives may be split over several headers
+        let split = headers(&[(CACHE_CONTROL, "max-age=60"), (CACHE_CONTROL, "no-store")]);
+        assert_eq!(CacheControl::parse(&split), CacheControl::NoStore);
+    }
+
+    #[test]
+    fn new_uses_default_ttl() {
+        let response = CachedResponse::new(Bytes::from("body"), &HeaderMap::new(), HOUR).unwrap();
+        assert!(response.is_fresh());
+        assert!(!response.can_revalidate());
+        let expires = response.expires.duration_since(SystemTime::now()).unwrap();
+        assert!(expires > HOUR - Duration::from_secs(5) && expires <= HOUR);
+    }
+
+    #[test]
+    fn new_uses_max_age() {
+        let response = CachedResponse::new(
+            Bytes::new(),
+            &headers(&[(CACHE_CONTROL, "max-age=0")]),
+            HOUR,
+        );
+        // would always need revalidation, but has no validators
+        assert_eq!(response, None);
+
+        let headers = headers(&[(CACHE_CONTROL, "max-age=0"), (ETAG, "\"v1\"")]);
+        let response = CachedResponse::new(Bytes::new(), &headers, HOUR).unwrap();
+        assert!(!response.is_fresh());
+        assert!(response.can_revalidate());
+        assert_eq!(response.etag.as_deref(), Some("\"v1\""));
+    }
+
+    #[test]
+    fn new_zero_ttl() {
+        assert_eq!(
+            CachedResponse::new(Bytes::new(), &HeaderMap::new(), Duration::ZERO),
+            None
+        );
+
+        let headers = headers(&[(LAST_MODIFIED, "Wed, 21 Oct 2015 07:28:00 GMT")]);
+        let response = CachedResponse::new(Bytes::new(), &headers, Duration::ZERO).unwrap();
+        assert!(!response.is_fresh());
+        assert!(response.can_revalidate());
+    }
+
+    #[test]
+    fn new_no_store() {
+        let headers = headers(&[(CACHE_CONTROL, "no-store"), (ETAG, "\"v1\"")]);
+        assert_eq!(CachedResponse::new(Bytes::new(), &headers, HOUR), None);
+    }
+
+    #[test]
+    fn max_age_overflow() {
+        let forever = headers(&[(CACHE_CONTROL, "max-age=18446744073709551615")]);
+        assert_eq!(
+            CacheControl::parse(&forever),
+            CacheControl::MaxAge(Duration::from_secs(u64::MAX))
+        );
+        assert_eq!(CachedResponse::new(Bytes::new(), &forever, HOUR), None);
+
+        let mut response =
+            CachedResponse::new(Bytes::new(), &stale_with_etag("\"v1\""), HOUR).unwrap();
+        assert!(!response.revalidate(&forever, HOUR));
+    }
+
+    #[test]
+    fn revalidate() {
+        let stale = headers(&[(CACHE_CONTROL, "no-cache"), (ETAG, "\"v1\"")]);
+        let mut response = CachedResponse::new(Bytes::from("body"), &stale, HOUR).unwrap();
+        assert!(!response.is_fresh());
+
+        // a 304 without caching headers extends the response by the default TTL
+        assert!(response.revalidate(&HeaderMap::new(), HOUR));
+        assert!(response.is_fresh());
+        assert_eq!(response.etag.as_deref(), Some("\"v1\""));
+        assert_eq!(response.body, Bytes::from("body"));
+
+        // and may replace the ETag or require revalidation again
+        assert!(response.revalidate(&stale_with_etag("\"v2\""), HOUR));
+        assert!(!response.is_fresh());
+        assert_eq!(response.etag.as_deref(), Some("\"v2\""));
+        assert_eq!(response.last_modified, None);
+
+        // as well as the modification time, keeping the ETag if not given
+        let modified = "Tue, 14 Mar 2023 12:00:00 GMT";
+        assert!(response.revalidate(&headers(&[(LAST_MODIFIED, modified)]), HOUR));
+        assert_eq!(response.last_modified.as_deref(), Some(modified));
+        assert_eq!(response.etag.as_deref(), Some("\"v2\""));
+        let modified = "Wed, 15 Mar 2023 12:00:00 GMT";
+        assert!(response.revalidate(&headers(&[(LAST_MODIFIED, modified)]), HOUR));
+        assert_eq!(response.last_modified.as_deref(), Some(modified));
+
+        let no_store = headers(&[(CACHE_CONTROL, "no-store")]);
+        assert!(!response.revalidate(&no_store, HOUR));
+    }
+
+    fn stale_with_etag(etag: &str) -> HeaderMap {
+        headers(&[(CACHE_CONTROL, "max-age=0"), (ETAG, etag)])
+    }
+
+    /// A fresh directory for a [`DiskCache`], removed when dropped.
+    struct TempDir(PathBuf);
+
+    impl TempDir {
+        fn new(name: &str) -> Self {
+            let dir =
+                std::env::temp_dir().join(format!("transitworld-{}-{}", name, std::process::id()));
+            let _ = fs::remove_dir_all(&dir);
+            TempDir(dir)
+        }
+    }
+
+    impl Drop for TempDir {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.0);
+        }
+    }
+
+    fn cached(body: &'static str) -> CachedResponse {
+        CachedResponse {
+            body: Bytes::from(body),
+            etag: Some("\"v1\"".into()),
+            last_modified: None,
+            expires: UNIX_EPOCH + Duration::from_secs(1_700_000_000),
+        }
+    }
+
+    #[test]
+    fn disk_cache() {
+        let dir = TempDir::new("disk-cache");
+        let cache = DiskCache::new(&dir.0).unwrap();
+        assert_eq!(cache.get("a"), None);
+
+        cache.put("a", cached("first"));
+        cache.put("b", cached("second"));
+        assert_eq!(cache.get("a"), Some(cached("first")));
+
+        // responses persist across instances
+        let cache = DiskCache::new(&dir.0).unwrap();
+        assert_eq!(cache.get("b"), Some(cached("second")));
+        cache.remove("b");
+        assert_eq!(cache.get("b"), None);
+    }
+
+    #[test]
+    fn disk_cache_eviction() {
+        let dir = TempDir::new("disk-cache-eviction");
+        let cache = DiskCache::new(&dir.0).unwrap();
+        cache.put("a", cached("first"));
+        let size = fs::metadata(cache.path("a")).unwrap().len();
+        let cache = cache.with_max_size(size * 2);
+
+        let mut modified = SystemTime::now() - Duration::from_secs(60);
+        for key in ["a", "b"] {
+            cache.put(key, cached("first"));
+            File::options()
+                .append(true)
+                .open(cache.path(key))
+                .unwrap()
+                .set_modified(modified)
+                .unwrap();
+            modified += Duration::from_secs(10);
+        }
+
+        // reading "a" makes "b" the least recently used
+        assert!(cache.get("a").is_some());
+        cache.put("c", cached("first"));
+        assert!(cache.get("a").is_some());
+        assert_eq!(cache.get("b"), None);
+        assert!(cache.get("c").is_some());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn disk_cache_read_only() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = TempDir::new("disk-cache-read-only");
+        let cache = DiskCache::new(&dir.0).unwrap();
+        cache.put("a", cached("first"));
+
+        let path = cache.path("a");
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o444)).unwrap();
+        fs::set_permissions(&dir.0, fs::Permissions::from_mode(0o555)).unwrap();
+        let result = cache.get("a");
+        fs::set_permissions(&dir.0, fs::Permissions::from_mode(0o755)).unwrap();
+        assert_eq!(result, Some(cached("first")));
+    }
+}
@@ -0,0 +1,650 @@
+use std::{
+    collections::HashMap,
+    fmt,
+    future::Future,
+    sync::{Arc, Mutex, OnceLock},
+    time::Duration,
+};
+
+use bytes::Bytes;
+use futures::Stream;
+use reqwest::{
+    header::{HeaderMap, IF_MODIFIED_SINCE, IF_NONE_MATCH},
+    RequestBuilder, Response, StatusCode, Url,
+};
+
+use crate::{
+    cache::{Cache, CachedResponse, MemoryCache},
+    error::{Error, Result},
+    rate_limit::RateLimiter,
+    DeparturesRequest, Quota, RateLimitStats, Request, RetryPolicy, SearchResponse,
+    TransitlandObject,
+};
+
+const TRANSITLAND_BASE_URL: &str = "https://transit.land/api/v2/rest";
+
+const DEFAULT_USER_AGENT: &str = concat!(env!("CARGO_PKG_NAME"), "/", env!("CARGO_PKG_VERSION"));
+
+/// A Transitland API client.
+///
+/// Clients hold a pool of connections along with the API key and base URL
+/// used for every request, so they should be created once and reused. Cloning
+/// a client is cheap and shares the underlying pool.
+#[derive(Debug, Clone)]
+pub struct Client {
+    inner: Arc<ClientInner>,
+}
+
+struct ClientInner {
+    http: reqwest::Client,
+    api_key: String,
+    base_url: String,
+    retry_policy: RetryPolicy,
+    rate_limiter: RateLimiter,
+    cache: Option<Arc<dyn Cache>>,
+    offline_fallback: bool,
+}
+
+impl fmt::Debug for ClientInner {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // leave out the API key so that clients can be logged safely
+        f.debug_struct("ClientInner")
+            .field("http", &self.http)
+            .field("base_url", &self.base_url)
+            .field("retry_policy", &self.retry_policy)
+            .field("rate_limiter", &self.rate_limiter)
+            .field("cache", &self.cache)
+            .field("offline_fallback", &self.offline_fallback)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Client {
+    /// Create a client with the default configuration.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the underlying HTTP client cannot be initialized. Use
+    /// [`Client::builder`] to handle this error instead.
+    pub fn new(api_key: impl Into<String>) -> Self {
+        Client::builder(api_key)
+            .build()
+            .expect("failed to initialize HTTP client")
+    }
+
+    pub fn builder(api_key: impl Into<String>) -> ClientBuilder {
+        ClientBuilder::new(api_key)
+    }
+
+    /// The client with the default configuration shared by the top-level
+    /// [`search`](crate::search) and [`get`](crate::get) functions, created
+    /// the first time `api_key` is used.
+    pub(crate) fn shared(api_key: &str) -> Self {
+        static CLIENTS: OnceLock<Mutex<HashMap<String, Client>>> = OnceLock::new();
+        CLIENTS
+            .get_or_init(Default::default)
+            .lock()
+            .unwrap()
+            .entry(api_key.to_owned())
+            .or_insert_with(|| Client::new(api_key))
+            .clone()
+    }
+
+    /// Start building a request sent through this client.
+    pub fn request(&self) -> Request {
+        Request::new(self.clone())
+    }
+
+    pub async fn search<T: TransitlandObject<()>>(&self, query: &str) -> Result<SearchResponse<T>> {
+        self.request().search(query).await
+    }
+
+    pub async fn search_filtered<T: TransitlandObject<()>>(
+        &self,
+        query: &str,
+        filter: &T::Query,
+    ) -> Result<SearchResponse<T>> {
+        self.request().search_filtered(query, filter).await
+    }
+
+    pub fn search_stream<T: TransitlandObject<()>>(
+        &self,
+        query: &str,
+    ) -> impl Stream<Item = Result<T>> {
+        self.request().search_stream(query)
+    }
+
+    pub async fn get<T: TransitlandObject<()>>(&self, key: &str) -> Result<Option<T>> {
+        self.request().get(key).await
+    }
+
+    /// Start building a request for the departures from a stop.
+    pub fn departures(&self) -> DeparturesRequest {
+        self.request().departures()
+    }
+
+    /// Statistics on how long requests have waited to stay within the
+    /// client's quotas, shared by all of its clones.
+    pub fn rate_limit_stats(&self) -> RateLimitStats {
+        self.inner.rate_limiter.stats()
+    }
+
+    /// Send an authenticated GET request for the given API path, returning
+    /// the body of a successful response.
+    ///
+    /// Responses are served from the client's [`Cache`] while fresh, and are
+    /// otherwise cached for `ttl` unless the server says otherwise. Requests
+    /// wait as needed to stay within the client's quotas, and failed requests
+    /// are retried according to its [`RetryPolicy`].
+    pub(crate) async fn fetch(
+        &self,
+        base_url: Option<&str>,
+        path: &str,
+        params: &[(&str, String)],
+        ttl: Duration,
+        revalidate: bool,
+    ) -> Result<Bytes> {
+        let url = format!(
+            "{}/{}",
+            base_url
+                .unwrap_or(&self.inner.base_url)
+                .trim_end_matches('/'),
+            path
+        );
+
+        let key = cache_key(&url, params);
+        let cached = self.inner.cache.as_ref().and_then(|cache| cache.get(&key));
+        if let Some(cached) = cached.as_ref().filter(|cached| cached.is_fresh()) {
+            if !revalidate {
+                return Ok(cached.body.clone());
+            }
+        }
+        let validators = cached.as_ref().filter(|cached| cached.can_revalidate());
+
+        let (headers, body) = match self
+            .with_retries(|| self.fetch_once(&url, params, validators))
+            .await
+        {
+            Ok(response) => response,
+            // fall back to stale data if the API can't be reached, unless the
+            // caller asked for the current data
+            Err(Error::Transport(_))
+                if self.inner.offline_fallback && !revalidate && cached.is_some() =>
+            {
+                return Ok(cached.unwrap().body)
+            }
+            Err(err) => return Err(err),
+        };
+
+        let Some(cache) = &self.inner.cache else {
+            // without a cache there are no validators, so there is always a body
+            return Ok(body.unwrap_or_default());
+        };
+        match (body, cached) {
+            (None, Some(mut cached)) => {
+                if cached.revalidate(&headers, ttl) {
+                    cache.put(&key, cached.clone());
+                } else {
+                    cache.remove(&key);
+                }
+                Ok(cached.body)
+            }
+            (body, _) => {
+                let body = body.unwrap_or_default();
+                match CachedResponse::new(body.clone(), &headers, ttl) {
+                    Some(response) => cache.put(&key, response),
+                    None => cache.remove(&key),
+                }
+                Ok(body)
+            }
+        }
+    }
+
+    /// Send an authenticated GET request for the given API path, returning
+    /// the successful response without reading its body.
+    ///
+    /// Unlike [`Client::fetch`], responses are never cached.
+    pub(crate) async fn fetch_response(
+        &self,
+        base_url: Option<&str>,
+        path: &str,
+    ) -> Result<Response> {
+        let url = format!(
+            "{}/{}",
+            base_url
+                .unwrap_or(&self.inner.base_url)
+                .trim_end_matches('/'),
+            path
+        );
+        self.with_retries(|| self.send_once(&url, &[], None)).await
+    }
+
+    /// The underlying HTTP client.
+    pub(crate) fn http(&self) -> &reqwest::Client {
+        &self.inner.http
+    }
+
+    /// Send a request to a URL outside the Transitland API, such as a feed's
+    /// realtime URLs, returning the body of the successful response.
+    ///
+    /// The request is retried according to the client's [`RetryPolicy`], but
+    /// is sent without the API key and is not cached or rate limited.
+    pub(crate) async fn fetch_external<F>(&self, build: F) -> Result<Bytes>
+    where
+        F: Fn(&reqwest::Client) -> Result<RequestBuilder>,
+    {
+        self.with_retries(|| async {
+            let response = build(&self.inner.http)?.send().await?;
+            if !response.status().is_success() {
+                return Err(Error::from_response(response).await);
+            }
+            Ok(response.bytes().await?)
+        })
+        .await
+    }
+
+    /// Run `f` until it succeeds or fails with an error which should not be
+    /// retried according to the client's [`RetryPolicy`].
+    async fn with_retries<F, Fut, T>(&self, mut f: F) -> Result<T>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<T>>,
+    {
+        let mut attempt = 1;
+        loop {
+            match f().await {
+                Ok(value) => return Ok(value),
+                Err(err) => match self.inner.retry_policy.delay(attempt, &err) {
+                    Some(delay) => tokio::time::sleep(delay).await,
+                    None => return Err(err),
+                },
+            }
+            attempt += 1;
+        }
+    }
+
+    /// Send a single request, returning the headers and body of a successful
+    /// response, or no body if `cached` was not modified.
+    async fn fetch_once(
+        &self,
+        url: &str,
+        params: &[(&str, String)],
+        cached: Option<&CachedResponse>,
+    ) -> Result<(HeaderMap, Option<Bytes>)> {
+        let response = self.send_once(url, params, cached).await?;
+        let headers = response.headers().clone();
+        if response.status() == StatusCode::NOT_MODIFIED {
+            return Ok((headers, None));
+        }
+        Ok((headers, Some(response.bytes().await?)))
+    }
+
+    /// Send a single request, returning the response if it was successful or
+    /// reported that `cached` was not modified.
+    async fn send_once(
+        &self,
+        url: &str,
+        params: &[(&str, String)],
+        cached: Option<&CachedResponse>,
+    ) -> Result<Response> {
+        self.inner.rate_limiter.acquire().await;
+
+        let mut request = self
+            .inner
+            .http
+            .get(url)
+            .query(&[("apikey", &self.inner.api_key)])
+            .query(params);
+        if let Some(cached) = cached {
+            if let Some(etag) = &cached.etag {
+                request = request.header(IF_NONE_MATCH, etag);
+            }
+            if let Some(last_modified) = &cached.last_modified {
+                request = request.header(IF_MODIFIED_SINCE, last_modified);
+            }
+        }
+        let response = request.send().await?;
+
+        if response.status() == StatusCode::NOT_MODIFIED && cached.is_some() {
+            return Ok(response);
+        }
+        if !response.status().is_success() {
+            return Err(Error::from_response(response).await);
+        }
+        Ok(response)
+    }
+}
+
+/// Builder for a [`Client`].
+#[derive(Debug)]
+pub struct ClientBuilder {
+    api_key: String,
+    base_url: String,
+    user_agent: String,
+    timeout: Option<Duration>,
+    connect_timeout: Option<Duration>,
+    retry_policy: RetryPolicy,
+    quotas: Vec<Quota>,
+    cache: Option<Arc<dyn Cache>>,
+    offline_fallback: bool,
+}
+
+impl ClientBuilder {
+    fn new(api_key: impl Into<String>) -> Self {
+        ClientBuilder {
+            api_key: api_key.into(),
+            base_url: TRANSITLAND_BASE_URL.to_owned(),
+            user_agent: DEFAULT_USER_AGENT.to_owned(),
+            timeout: None,
+            connect_timeout: None,
+            retry_policy: RetryPolicy::default(),
+            quotas: vec![],
+            cache: Some(Arc::new(MemoryCache::default())),
+            offline_fallback: false,
+        }
+    }
+
+    /// Use a different base URL, such as that of a self-hosted
+    /// transitland-server.
+    pub fn with_base_url(mut self, base_url: impl Into<String>) -> Self {
+        self.base_url = base_url.into();
+        self
+    }
+
+    pub fn with_user_agent(mut self, user_agent: impl Into<String>) -> Self {
+        self.user_agent = user_agent.into();
+        self
+    }
+
+    /// Time out requests which have not completed after the given duration.
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Time out requests which have not connected after the given duration.
+    pub fn with_connect_timeout(mut self, connect_timeout: Duration) -> Self {
+        self.connect_timeout = Some(connect_timeout);
+        self
+    }
+
+    /// Retry failed requests according to the given policy.
+    pub fn with_retry_policy(mut self, retry_policy: RetryPolicy) -> Self {
+        self.retry_policy = retry_policy;
+        self
+    }
+
+    /// Limit the rate of requests to the given quota, such as that of the API
+    /// key. May be called more than once to enforce several quotas.
+    ///
+    /// Requests which would exceed a quota wait until they can be sent.
+    pub fn with_quota(mut self, quota: Quota) -> Self {
+        self.quotas.push(quota);
+        self
+    }
+
+    /// Store responses in the given cache instead of the default
+    /// [`MemoryCache`].
+    pub fn with_cache(mut self, cache: impl Cache + 'static) -> Self {
+        self.cache = Some(Arc::new(cache));
+        self
+    }
+
+    /// Do not cache responses.
+    pub fn without_cache(mut self) -> Self {
+        self.cache = None;
+        self
+    }
+
+    /// Serve expired responses from the cache when the API cannot be reached,
+    /// rather than failing with [`Error::Transport`]. Disabled by default.
+    ///
+    /// Together with a [`DiskCache`](crate::cache::DiskCache), this allows
+    /// working offline from previously fetched data. Requests which ask for
+    /// revalidation, such as with
+    /// [`Request::with_revalidation`](crate::Request::with_revalidation), still
+    /// fail.
+    pub fn with_offline_fallback(mut self, offline_fallback: bool) -> Self {
+        self.offline_fallback = offline_fallback;
+        self
+    }
+
+    pub fn build(self) -> Result<Client> {
+        let mut http = reqwest::Client::builder().user_agent(self.user_agent);
+        if let Some(timeout) = self.timeout {
+            http = http.timeout(timeout);
+        }
+        if let Some(connect_timeout) = self.connect_timeout {
+            http = http.connect_timeout(connect_timeout);
+        }
+
+        Ok(Client {
+            inner: Arc::new(ClientInner {
+                http: http.build()?,
+                api_key: self.api_key,
+                base_url: self.base_url,
+                retry_policy: self.retry_policy,
+                rate_limiter: RateLimiter::new(&self.quotas),
+                cache: self.cache,
+                offline_fallback: self.offline_fallback,
+            }),
+        })
+    }
+}
+
+/// Key identifying a request in a [`Cache`], independent of the order of its
+/// query parameters and excluding the API key.
+fn cache_key(url: &str, params: &[(&str, String)]) -> String {
+    let mut params = params.to_vec();
+    params.sort();
+    Url::parse_with_params(url, &params)
+        .map(String::from)
+        .unwrap_or_else(|_| url.to_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use tokio::net::TcpStream;
+
+    use super::*;
+    use crate::test_server::{response, test_client, test_client_builder, TestServer};
+
+    const HOUR: Duration = Duration::from_secs(60 * 60);
+
+    async fn fetch(client: &Client, ttl: Duration, revalidate: bool) -> Result<Bytes> {
+        let params = [("limit", "1".to_owned())];
+        client.fetch(None, "feeds", &params, ttl, revalidate).await
+    }
+
+    #[test]
+    fn shared_clients() {
+        let client = Client::shared("shared");
+        assert!(Arc::ptr_eq(&client.inner, &Client::shared("shared").inner));
+        assert!(!Arc::ptr_eq(&client.inner, &Client::shared("other").inner));
+    }
+
+    #[tokio::test]
+    async fn fresh_responses_are_reused() {
+        let server = TestServer::start(vec![response(
+            200,
+            &[("Cache-Control", "max-age=60"), ("ETag", "\"v1\"")],
+            "first",
+        )])
+        .await;
+        let client = test_client(server.url());
+
+        assert_eq!(fetch(&client, HOUR, false).await.unwrap(), "first");
+        assert_eq!(fetch(&client, HOUR, false).await.unwrap(), "first");
+        let requests = server.requests();
+        assert_eq!(requests.len(), 1);
+        assert!(requests[0].starts_with("get /feeds?apikey=secret&limit=1 "));
+    }
+
+    #[tokio::test]
+    async fn stale_responses_are_revalidated() {
+        let server = TestServer::start(vec![
+            response(
+                200,
+                &[("Cache-Control", "no-cache"), ("ETag", "\"v1\"")],
+                "first",
+            ),
+            response(304, &[("Cache-Control", "max-age=60")], ""),
+        ])
+        .await;
+        let client = test_client(server.url());
+
+        assert_eq!(fetch(&client, HOUR, false).await.unwrap(), "first");
+        // not modified, so the cached body is used and is fresh again
+        assert_eq!(fetch(&client, HOUR, false).await.unwrap(), "first");
+        assert_eq!(fetch(&client, HOUR, false).await.unwrap(), "first");
+
+        let requests = server.requests();
+        assert_eq!(requests.len(), 2);
+        assert!(!requests[0].contains("if-none-match"));
+        assert!(requests[1].contains("if-none-match: \"v1\"\r\n"));
+    }
+
+    #[tokio::test]
+    async fn modified_responses_replace_cache() {
+        let server = TestServer::start(vec![
+            response(
+                200,
+                &[("Cache-Control", "no-cache"), ("ETag", "\"v1\"")],
+                "first",
+            ),
+            response(
+                200,
+                &[("Cache-Control", "no-cache"), ("ETag", "\"v2\"")],
+                "second",
+            ),
+            response(304, &[], ""),
+        ])
+        .await;
+        let client = test_client(server.url());
+
+        assert_eq!(fetch(&client, HOUR, false).await.unwrap(), "first");
+        assert_eq!(fetch(&client, HOUR, false).await.unwrap(), "second");
+        assert_eq!(fetch(&client, HOUR, false).await.unwrap(), "second");
+        assert!(server.requests()[2].contains("if-none-match: \"v2\"\r\n"));
+    }
+
+    #[tokio::test]
+    async fn revalidate_skips_fresh_responses() {
+        let server = TestServer::start(vec![
+            response(
+                200,
+                &[
+                    ("Cache-Control", "max-age=60"),
+                    ("Last-Modified", "Wed, 21 Oct 2015 07:28:00 GMT"),
+                ],
+                "first",
+            ),
+            response(304, &[], ""),
+        ])
+        .await;
+        let client = test_client(server.url());
+
+        assert_eq!(fetch(&client, HOUR, false).await.unwrap(), "first");
+        assert_eq!(fetch(&client, HOUR, true).await.unwrap(), "first");
+        let requests = server.requests();
+        assert_eq!(requests.len(), 2);
+        assert!(requests[1].contains("if-modified-since: wed, 21 oct 2015 07:28:00 gmt\r\n"));
+    }
+
+    #[tokio::test]
+    async fn no_store() {
+        let no_store = [("Cache-Control", "no-store"), ("ETag", "\"v1\"")];
+        let server = TestServer::start(vec![
+            response(200, &no_store, "first"),
+            response(200, &no_store, "second"),
+        ])
+        .await;
+        let client = test_client(server.url());
+
+        assert_eq!(fetch(&client, HOUR, false).await.unwrap(), "first");
+        assert_eq!(fetch(&client, HOUR, false).await.unwrap(), "second");
+        assert!(!server.requests()[1].contains("if-none-match"));
+    }
+
+    #[tokio::test]
+    async fn zero_ttl_without_validators() {
+        let server = TestServer::start(vec![
+            response(200, &[], "first"),
+            response(200, &[], "second"),
+        ])
+        .await;
+        let client = test_client(server.url());
+
+        assert_eq!(
+            fetch(&client, Duration::ZERO, false).await.unwrap(),
+            "first"
+        );
+        assert_eq!(
+            fetch(&client, Duration::ZERO, false).await.unwrap(),
+            "second"
+        );
+        assert_eq!(server.requests().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn errors_are_not_cached() {
+        let server = TestServer::start(vec![
+            response(
+                404,
+                &[("Cache-Control", "max-age=60")],
+                "{\"error\": \"no feed\"}",
+            ),
+            response(200, &[], "found"),
+        ])
+        .await;
+        let client = test_client(server.url());
+
+        assert!(matches!(
+            fetch(&client, HOUR, false).await,
+            Err(Error::NotFound(_))
+        ));
+        assert_eq!(fetch(&client, HOUR, false).await.unwrap(), "found");
+    }
+
+    async fn cached_then_unreachable(offline_fallback: bool) -> Client {
+        let server = TestServer::start(vec![response(
+            200,
+            &[("Cache-Control", "no-cache"), ("ETag", "\"v1\"")],
+            "first",
+        )])
+        .await;
+        let client = test_client_builder(server.url())
+            .with_offline_fallback(offline_fallback)
+            .build()
+            .unwrap();
+        assert_eq!(fetch(&client, HOUR, false).await.unwrap(), "first");
+        // the server stops listening once it has sent its responses
+        while TcpStream::connect(server.url().trim_start_matches("http://"))
+            .await
+            .is_ok()
+        {
+            tokio::task::yield_now().await;
+        }
+        client
+    }
+
+    #[tokio::test]
+    async fn offline_fallback_is_opt_in() {
+        let client = cached_then_unreachable(false).await;
+        assert!(matches!(
+            fetch(&client, HOUR, false).await,
+            Err(Error::Transport(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn offline_fallback() {
+        let client = cached_then_unreachable(true).await;
+        assert_eq!(fetch(&client, HOUR, false).await.unwrap(), "first");
+        // but not when the caller asked for current data
+        assert!(matches!(
+            fetch(&client, HOUR, true).await,
+            Err(Error::Transport(_))
+        ));
+    }
+}
@@ -0,0 +1,124 @@
+//! Evaluating which dates a service runs on.
+
+use std::collections::BTreeSet;
+
+use chrono::{Datelike, NaiveDate, Weekday};
+
+use super::Calendar;
+
+impl Calendar {
+    /// Whether service is scheduled on the given day of the week, between
+    /// [`start_date`](Self::start_date) and [`end_date`](Self::end_date).
+    pub fn runs_on_weekday(&self, weekday: Weekday) -> bool {
+        let flag = match weekday {
+            Weekday::Mon => self.monday,
+            Weekday::Tue => self.tuesday,
+            Weekday::Wed => self.wednesday,
+            Weekday::Thu => self.thursday,
+            Weekday::Fri => self.friday,
+            Weekday::Sat => self.saturday,
+            Weekday::Sun => self.sunday,
+        };
+        flag == 1
+    }
+
+    /// Whether service runs on the given date.
+    ///
+    /// Added and removed dates take precedence over the weekly schedule, and
+    /// may fall outside of its date range.
+    pub fn is_active_on(&self, date: NaiveDate) -> bool {
+        if self
+            .removed_dates
+            .iter()
+            .flatten()
+            .any(|removed| *removed == date)
+        {
+            return false;
+        }
+        if self
+            .added_dates
+            .iter()
+            .flatten()
+            .any(|added| *added == date)
+        {
+            return true;
+        }
+        (self.start_date..=self.end_date).contains(&date) && self.runs_on_weekday(date.weekday())
+    }
+
+    /// Iterate over every date that service runs on, in order.
+    pub fn service_dates(&self) -> ServiceDates<'_> {
+        let added = self.added_dates.iter().flatten();
+        ServiceDates {
+            calendar: self,
+            next: added.clone().copied().fold(self.start_date, NaiveDate::min),
+            end: added.copied().fold(self.end_date, NaiveDate::max),
+        }
+    }
+
+    /// A [generated](Self::generated) calendar running on every date that
+    /// either calendar runs on.
+    pub fn union(&self, other: &Calendar) -> Calendar {
+        let dates = self.service_dates().chain(other.service_dates());
+        Calendar::from_dates(dates, self.start_date.min(other.start_date))
+    }
+
+    /// A [generated](Self::generated) calendar running on every date that both
+    /// calendars run on.
+    pub fn intersection(&self, other: &Calendar) -> Calendar {
+        let dates = self
+            .service_dates()
+            .filter(|date| other.is_active_on(*date));
+        Calendar::from_dates(dates, self.start_date.max(other.start_date))
+    }
+
+    /// A calendar running on exactly the given dates, with no weekly schedule
+    /// or service ID. If there are no dates, its range is the single date
+    /// `empty`.
+    fn from_dates(dates: impl IntoIterator<Item = NaiveDate>, empty: NaiveDate) -> Calendar {
+        let dates: BTreeSet<_> = dates.into_iter().collect();
+        Calendar {
+            service_id: None,
+            start_date: dates.first().copied().unwrap_or(empty),
+            end_date: dates.last().copied().unwrap_or(empty),
+            added_dates: Some(dates.into_iter().collect()),
+            removed_dates: None,
+            generated: Some(true),
+            monday: 0,
+            tuesday: 0,
+            wednesday: 0,
+            thursday: 0,
+            friday: 0,
+            saturday: 0,
+            sunday: 0,
+        }
+    }
+}
+
+/// Iterator over the dates a [`Calendar`] runs on.
+///
+/// See also: [`Calendar::service_dates`]
+#[derive(Debug, Clone)]
+pub struct ServiceDates<'a> {
+    calendar: &'a Calendar,
+    next: NaiveDate,
+    end: NaiveDate,
+}
+
+impl Iterator for ServiceDates<'_> {
+    type Item = NaiveDate;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while self.next <= self.end {
+            let date = self.next;
+            match date.succ_opt() {
+                Some(next) => self.next = next,
+                None => self.end = date.pred_opt()?,
+            }
+            if self.calendar.is_active_on(date) {
+                return Some(date);
+            }
+        }
+        None
+    }
+}
@@ -0,0 +1,211 @@
+//! Enumerated values of GTFS fields.
+//!
+//! Each type converts to and from the integer used by GTFS, and is
+//! (de)serialized as that integer. Values not defined by the specification
+//! are kept as `Unknown`, so that they survive a round trip.
+
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+macro_rules! gtfs_enum {
+    (
+        $(#[$meta:meta])*
+        pub enum $name:ident {
+            $($(#[$variant_meta:meta])* $variant:ident = $value:literal,)*
+        }
+    ) => {
+        $(#[$meta])*
+        #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize, Serialize)]
+        #[serde(from = "u64", into = "u64")]
+        pub enum $name {
+            $($(#[$variant_meta])* $variant,)*
+            /// A value not defined by the specification.
+            Unknown(u64),
+        }
+
+        impl From<u64> for $name {
+            fn from(value: u64) -> Self {
+                match value {
+                    $($value => $name::$variant,)*
+                    value => $name::Unknown(value),
+                }
+            }
+        }
+
+        impl From<$name> for u64 {
+            fn from(value: $name) -> Self {
+                match value {
+                    $($name::$variant => $value,)*
+                    $name::Unknown(value) => value,
+                }
+            }
+        }
+
+        impl fmt::Display for $name {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                write!(f, "{}", u64::from(*self))
+            }
+        }
+    };
+}
+
+/// GTFS `route_type`: the kind of vehicle used on a route.
+///
+/// Besides the basic types, the [extended route
+/// types](https://developers.google.com/transit/gtfs/reference/extended-route-types)
+/// from 100 to 1700 are accepted by many consumers, including Transitland.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize, Serialize)]
+#[serde(from = "u64", into = "u64")]
+pub enum RouteType {
+    /// Tram, streetcar or light rail.
+    Tram,
+    /// Subway or metro.
+    Subway,
+    /// Intercity or long-distance rail.
+    Rail,
+    Bus,
+    Ferry,
+    /// Street-level rail car pulled by a cable under the street.
+    CableTram,
+    /// Aerial lift, such as a gondola or aerial tramway.
+    AerialLift,
+    /// Rail designed for steep inclines.
+    Funicular,
+    /// Electric bus drawing power from overhead wires.
+    Trolleybus,
+    Monorail,
+    /// An extended route type, between 100 and 1700.
+    Extended(u64),
+    /// A value not defined by the specification.
+    Unknown(u64),
+}
+
+impl RouteType {
+    /// The basic route type closest to this one, if known.
+    ///
+    /// Extended route types are grouped by hundreds, such as 700 to 799 for
+    /// buses.
+    pub fn basic(&self) -> Option<RouteType> {
+        match self {
+            RouteType::Extended(value) => match value / 100 {
+                1 | 3 => Some(RouteType::Rail),
+                2 | 7 => Some(RouteType::Bus),
+                4..=6 => Some(RouteType::Subway),
+                8 => Some(RouteType::Trolleybus),
+                9 => Some(RouteType::Tram),
+                10 | 12 => Some(RouteType::Ferry),
+                13 => Some(RouteType::AerialLift),
+                14 => Some(RouteType::Funicular),
+                _ => None,
+            },
+            RouteType::Unknown(_) => None,
+            basic => Some(*basic),
+        }
+    }
+}
+
+impl From<u64> for RouteType {
+    fn from(value: u64) -> Self {
+        match value {
+            0 => RouteType::Tram,
+            1 => RouteType::Subway,
+            2 => RouteType::Rail,
+            3 => RouteType::Bus,
+            4 => RouteType::Ferry,
+            5 => RouteType::CableTram,
+            6 => RouteType::AerialLift,
+            7 => RouteType::Funicular,
+            11 => RouteType::Trolleybus,
+            12 => RouteType::Monorail,
+            100..=1700 => RouteType::Extended(value),
+            value => RouteType::Unknown(value),
+        }
+    }
+}
+
+impl From<RouteType> for u64 {
+    fn from(value: RouteType) -> Self {
+        match value {
+            RouteType::Tram => 0,
+            RouteType::Subway => 1,
+            RouteType::Rail => 2,
+            RouteType::Bus => 3,
+            RouteType::Ferry => 4,
+            RouteType::CableTram => 5,
+            RouteType::AerialLift => 6,
+            RouteType::Funicular => 7,
+            RouteType::Trolleybus => 11,
+            RouteType::Monorail => 12,
+            RouteType::Extended(value) | RouteType::Unknown(value) => value,
+        }
+    }
+}
+
+impl fmt::Display for RouteType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", u64::from(*self))
+    }
+}
+
+gtfs_enum! {
+    /// GTFS `location_type`: the kind of location a stop represents.
+    pub enum LocationType {
+        /// A location where passengers board or alight.
+        Stop = 0,
+        /// A structure or area containing one or more platforms.
+        Station = 1,
+        /// A location where passengers enter or exit a station.
+        Entrance = 2,
+        /// A location within a station used to link pathways.
+        GenericNode = 3,
+        /// A specific location on a platform.
+        BoardingArea = 4,
+    }
+}
+
+gtfs_enum! {
+    /// Whether a stop or trip is accessible, as in GTFS `wheelchair_boarding`,
+    /// `wheelchair_accessible` and `bikes_allowed`.
+    ///
+    /// For stops within a station, `NoInformation` means the value is
+    /// inherited from the station.
+    pub enum Accessibility {
+        NoInformation = 0,
+        Accessible = 1,
+        NotAccessible = 2,
+    }
+}
+
+gtfs_enum! {
+    /// GTFS `pickup_type` and `drop_off_type`: how passengers may board or
+    /// alight at a stop.
+    pub enum PickupDropOffType {
+        /// Regularly scheduled.
+        Regular = 0,
+        NotAvailable = 1,
+        /// Passengers must phone the agency to arrange it.
+        PhoneAgency = 2,
+        /// Passengers must coordinate with the driver to arrange it.
+        CoordinateWithDriver = 3,
+    }
+}
+
+gtfs_enum! {
+    /// GTFS `timepoint`: whether the times of a stop are exact.
+    pub enum Timepoint {
+        Approximate = 0,
+        Exact = 1,
+    }
+}
+
+gtfs_enum! {
+    /// GTFS `exact_times`: whether a frequency-based trip follows a fixed
+    /// schedule.
+    pub enum ExactTimes {
+        /// Trips run at approximately the given headway.
+        FrequencyBased = 0,
+        /// Trips depart at exactly the given headway from the start time.
+        ScheduleBased = 1,
+    }
+}
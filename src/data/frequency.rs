@@ -0,0 +1,89 @@
+//! Expanding frequency-based trips into individual trips.
+
+use std::iter;
+
+use super::{ExactTimes, Frequency, ServiceTime, StopTime, Trip};
+
+impl Frequency {
+    /// The time each trip departs from its first stop: every `headway_secs`
+    /// from `start_time`, until before `end_time`.
+    ///
+    /// For [`ExactTimes::FrequencyBased`] trips these times are approximate,
+    /// and only the headway is meaningful to riders.
+    pub fn start_times(&self) -> impl Iterator<Item = ServiceTime> {
+        let (end_time, headway) = (self.end_time, self.headway_secs);
+        let first = Some(self.start_time).filter(|_| headway > 0);
+        iter::successors(first, move |time| {
+            u32::try_from(headway)
+                .ok()
+                .and_then(|headway| time.as_secs().checked_add(headway))
+                .map(ServiceTime::from_secs)
+        })
+        .take_while(move |time| *time < end_time)
+    }
+}
+
+impl Trip {
+    /// Expand this trip into the individual trips it represents.
+    ///
+    /// A trip with [`frequencies`](Self::frequencies) is repeated from each
+    /// of its [start times](Frequency::start_times), with its stop times
+    /// shifted so that it departs its first stop at that time. A trip without
+    /// frequencies is returned unchanged, with [`ExactTimes::ScheduleBased`].
+    ///
+    /// Nothing is returned if the trip has no stop times.
+    pub fn expand_frequencies(&self) -> impl Iterator<Item = TripInstance> + '_ {
+        let stop_times = self.stop_times.as_deref().unwrap_or_default();
+        let first_departure = stop_times.first().map(|stop_time| stop_time.departure_time);
+
+        let scheduled = first_departure
+            .filter(|_| self.frequencies.is_empty())
+            .map(|start_time| (start_time, ExactTimes::ScheduleBased));
+        let repeated = self.frequencies.iter().flat_map(move |frequency| {
+            frequency
+                .start_times()
+                .filter(move |_| first_departure.is_some())
+                .map(move |start_time| (start_time, frequency.exact_times))
+        });
+
+        scheduled
+            .into_iter()
+            .chain(repeated)
+            .map(move |(start_time, exact_times)| {
+                let offset = i64::from(start_time.as_secs())
+                    - first_departure.map_or(0, |time| i64::from(time.as_secs()));
+                TripInstance {
+                    start_time,
+                    exact_times,
+                    stop_times: stop_times
+                        .iter()
+                        .map(|stop_time| StopTime {
+                            arrival_time: shift(stop_time.arrival_time, offset),
+                            departure_time: shift(stop_time.departure_time, offset),
+                            ..stop_time.clone()
+                        })
+                        .collect(),
+                }
+            })
+    }
+}
+
+/// Shift a time by a number of seconds, stopping at the start of the service
+/// day.
+fn shift(time: ServiceTime, offset: i64) -> ServiceTime {
+    let seconds = i64::from(time.as_secs()) + offset;
+    ServiceTime::from_secs(u32::try_from(seconds.max(0)).unwrap_or(u32::MAX))
+}
+
+/// A single run of a [`Trip`], as expanded from its frequencies.
+///
+/// See also: [`Trip::expand_frequencies`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct TripInstance {
+    /// When the trip departs its first stop.
+    pub start_time: ServiceTime,
+    /// Whether the stop times are exact, or only indicate the headway.
+    pub exact_times: ExactTimes,
+    /// The trip's stop times, shifted to begin at `start_time`.
+    pub stop_times: Vec<StopTime>,
+}
@@ -8,22 +8,43 @@
 //! - [`Route`]
 //! - [`Stop`]
 //! - [`Trip`]
+//! - [`Departure`]
 
 #![allow(dead_code)]
 
-use std::collections::HashMap;
+use std::{collections::HashMap, time::Duration};
 
-use chrono::{NaiveDate, NaiveDateTime};
-use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use chrono::{DateTime, NaiveDate, NaiveDateTime, Utc};
+use serde::{Deserialize, Serialize};
 use serde_json::Value;
 
-use crate::TransitlandObject;
+use crate::{query, TransitlandObject};
 
+mod calendar;
+mod enums;
+mod frequency;
 pub mod partial;
+mod time;
+#[cfg(feature = "chrono-tz")]
+mod tz;
+
+pub use calendar::*;
+pub use enums::*;
+pub use frequency::*;
+pub use time::*;
+#[cfg(feature = "chrono-tz")]
+pub use tz::*;
+
+#[cfg(feature = "chrono-tz")]
+pub use chrono_tz::Tz;
 
 macro_rules! impl_object {
-    ($type:path, $name:expr) => {
+    ($type:path, $name:expr, $query:ty $(, { $($item:item)* })?) => {
         impl crate::api::TransitlandObject<()> for $type {
+            $($($item)*)?
+
+            type Query = $query;
+
             fn query_path(_: ()) -> String {
                 $name.to_owned()
             }
@@ -36,7 +57,7 @@ macro_rules! impl_object {
 }
 
 /// Types of feed data (GTFS, GTFS-RT, GBFS, or MDS).
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
 #[serde(rename_all = "lowercase")]
 pub enum Spec {
     /// General Transit Feed Specification (GTFS). Its specification is
@@ -54,6 +75,18 @@ pub enum Spec {
     MDS,
 }
 
+impl Spec {
+    /// Name of this spec as used by the Transitland API.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Spec::GTFS => "gtfs",
+            Spec::GTFSRealtime => "gtfs-rt",
+            Spec::GBFS => "gbfs",
+            Spec::MDS => "mds",
+        }
+    }
+}
+
 /// Details on how to access transit information for a given feed.
 ///
 /// Feeds contain details on how to access transit information, including URLs
@@ -63,7 +96,7 @@ pub enum Spec {
 ///
 /// View its online documentation
 /// [here](https://www.transit.land/documentation/rest-api/feeds).
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
 pub struct Feed {
     /// Unique integer ID..
     pub id: u64,
@@ -90,7 +123,7 @@ pub struct Feed {
     /// resource.
     pub authorization: Authorization,
     /// Geometry in GeoJSON format.
-    pub geometry: Option<Geometry<Vec<Vec<(f64, f64)>>>>,
+    pub geometry: Option<Geometry<Polygon>>,
     /// Details on the current state of this feed, such as active version, last
     /// fetch time, etc.
     pub feed_state: FeedState,
@@ -98,77 +131,135 @@ pub struct Feed {
     pub feed_versions: Vec<partial::FeedVersion>,
 }
 
-impl_object!(Feed, "feeds");
+impl_object!(Feed, "feeds", query::FeedQuery, {
+    const ACCEPTS_SPEC: bool = true;
+    const CACHE_TTL: Duration = Duration::from_secs(60 * 60);
+});
 
 /// URls associated with a feed.
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize, Serialize)]
 pub struct Urls {
     /// URL for the static feed that represents today's service.
-    pub static_current: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub static_current: Option<String>,
     /// URLs for static feeds that represent past service no longer in effect.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
     pub static_historic: Vec<String>,
     /// URLs for static feeds that represent service planned for upcoming dates.
     /// Typically used to represent calendar/service changes that will take
     /// effect a few weeks or months in the future.
-    pub static_planned: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub static_planned: Option<String>,
+    /// URL for a static feed that represents potential service or network
+    /// changes, such as proposals that have not been adopted.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub static_hypothetical: Option<String>,
     /// URL for GTFS Realtime VehiclePosition messages.
-    pub realtime_vehicle_positions: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub realtime_vehicle_positions: Option<String>,
     /// URL for GTFS Realtime TripUpdate messages.
-    pub realtime_trip_updates: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub realtime_trip_updates: Option<String>,
     /// URL for GTFS Realtime Alert messages.
-    pub realtime_alerts: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub realtime_alerts: Option<String>,
+    /// URL for the GBFS auto-discovery file (`gbfs.json`).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub gbfs_auto_discovery: Option<String>,
 }
 
-// TODO download source GTFS
-
 /// Licensing information for feeds.
 ///
 /// You can view more about the licensing issues associated with Transitland
 /// data [here](https://www.transit.land/documentation/an-open-project/).
 ///
 /// See also: [`Feed`]
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize, Serialize)]
 pub struct License {
     /// SPDX identifier for a common license.
     /// See <https://spdx.org/licenses/>.
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub spdx_identifier: Option<String>,
     /// URL for a custom license.
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub url: Option<String>,
     /// Are feed consumers allowed to use the feed contents without including
     /// attribution text in their app or map?
-    pub use_without_attribution: Option<String>, // TODO yes, no, unknown type
+    #[serde(default, skip_serializing_if = "Permission::is_unknown")]
+    pub use_without_attribution: Permission,
     /// Are feed consumers allowed to create and share derived products from the feed?
-    pub create_derived_product: Option<String>,
+    #[serde(default, skip_serializing_if = "Permission::is_unknown")]
+    pub create_derived_product: Permission,
     /// Are feed consumers allowed to redistribute the feed in its entirety?
-    pub redistribution_allowed: Option<String>,
+    #[serde(default, skip_serializing_if = "Permission::is_unknown")]
+    pub redistribution_allowed: Permission,
     /// Are feed consumers allowed to use the feed for commercial purposes?
-    pub commercial_use_allowed: Option<String>,
+    #[serde(default, skip_serializing_if = "Permission::is_unknown")]
+    pub commercial_use_allowed: Permission,
     /// Are feed consumers allowed to keep their modifications of this feed private?
-    pub share_alike_optional: Option<String>,
+    #[serde(default, skip_serializing_if = "Permission::is_unknown")]
+    pub share_alike_optional: Permission,
     /// Feed consumers must include this particular text when using this feed.
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub attribution_text: Option<String>,
     /// Feed consumers must follow these instructions for how to provide attribution.
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub attribution_instructions: Option<String>,
 }
 
+/// Whether a license grants a permission.
+///
+/// See also: [`License`]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Permission {
+    Yes,
+    No,
+    /// The license has not been reviewed for this permission.
+    #[default]
+    Unknown,
+}
+
+impl Permission {
+    pub fn is_unknown(&self) -> bool {
+        *self == Permission::Unknown
+    }
+}
+
+impl<'de> Deserialize<'de> for Permission {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        // missing, empty and unrecognized values are all treated as unknown
+        let value = Option::<String>::deserialize(deserializer)?;
+        Ok(
+            match value.as_deref().map(str::to_ascii_lowercase).as_deref() {
+                Some("yes") => Permission::Yes,
+                Some("no") => Permission::No,
+                _ => Permission::Unknown,
+            },
+        )
+    }
+}
+
 /// Details on how to access a protected resource.
 ///
 /// See also: [`Feed`]
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize, Serialize)]
 pub struct Authorization {
     /// Method for inserting authorization secret into request.
-    #[serde(rename = "type")]
+    #[serde(rename = "type", skip_serializing_if = "Option::is_none")]
     pub auth_type: Option<AuthorizationType>,
     /// When type=query_param, this specifies the name of the query parameter.
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub param_name: Option<String>,
     /// Website to visit to sign up for an account.
-    pub info_url: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub info_url: Option<String>,
 }
 
 /// Type of authorization for a feed.
 ///
 /// See also: [`Authorization`], [`Feed`]
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
 #[serde(rename_all = "snake_case")]
 pub enum AuthorizationType {
     #[serde(rename = "")]
@@ -180,23 +271,23 @@ pub enum AuthorizationType {
 }
 
 /// Geometry in GeoJSON format.
-#[derive(Debug, Deserialize)]
-pub struct Geometry<C>
-where
-    C: DeserializeOwned,
-{
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct Geometry<C> {
     /// GeoJSON geometry type.
     #[serde(rename = "type")]
     pub type_: String,
     /// An array of GeoJSON coordinates.
-    #[serde(bound = "")]
     pub coordinates: C,
 }
 
+/// Coordinates of a GeoJSON polygon, as rings of `(longitude, latitude)`
+/// positions.
+pub type Polygon = Vec<Vec<(f64, f64)>>;
+
 /// Details on the state of a feed.
 ///
 /// See also: [`Feed`]
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
 pub struct FeedState {
     /// Error produced during the last fetch attempt. Empty string if no error.
     ///
@@ -225,7 +316,7 @@ pub struct FeedState {
 /// [here](https://www.transit.land/documentation/rest-api/feed_versions).
 ///
 /// See also: [`Feed`]
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
 pub struct FeedVersion {
     /// Unique integer ID.
     pub id: Option<u64>,
@@ -250,10 +341,12 @@ pub struct FeedVersion {
     pub feed: partial::Feed,
 }
 
-impl_object!(FeedVersion, "feed_versions");
+impl_object!(FeedVersion, "feed_versions", query::FeedVersionQuery, {
+    const CACHE_TTL: Duration = Duration::from_secs(24 * 60 * 60);
+});
 
 /// Metadata of archive files.
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
 pub struct FileMetadata {
     /// File name.
     ///
@@ -282,7 +375,7 @@ pub struct FileMetadata {
 ///
 /// View its online documentation
 /// [here](https://www.transit.land/documentation/rest-api/agencies).
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
 pub struct Agency {
     /// Unique integer ID.
     pub id: u64,
@@ -305,7 +398,7 @@ pub struct Agency {
     /// GTFS `agency_email`.
     pub agency_email: Option<String>,
     /// Geometry in GeoJSON format.
-    pub geometry: Option<Geometry<Vec<Vec<(f64, f64)>>>>,
+    pub geometry: Option<Geometry<Polygon>>,
     /// Subset of fields for operator, if matched.
     pub operator: Option<partial::Operator>,
     /// Structured array of places associated with this agency.
@@ -316,10 +409,13 @@ pub struct Agency {
     pub routes: Option<Vec<partial::Route>>,
 }
 
-impl_object!(Agency, "agencies");
+impl_object!(Agency, "agencies", query::AgencyQuery, {
+    const ACCEPTS_GEO: bool = true;
+    const CACHE_TTL: Duration = Duration::from_secs(60 * 60);
+});
 
 /// Place associated with an agency.
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
 pub struct Place {
     /// Best-matched city.
     pub city_name: Option<String>,
@@ -338,7 +434,7 @@ pub struct Place {
 ///
 /// View its online documentation
 /// [here](https://www.transit.land/documentation/rest-api/agencies).
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
 pub struct Operator {
     /// Unique integer ID.
     pub id: u64,
@@ -356,7 +452,10 @@ pub struct Operator {
     pub agencies: Option<Vec<partial::Agency>>,
 }
 
-impl_object!(Operator, "operators");
+impl_object!(Operator, "operators", query::OperatorQuery, {
+    const ACCEPTS_GEO: bool = true;
+    const CACHE_TTL: Duration = Duration::from_secs(60 * 60);
+});
 
 /// Representative of a GTFS `routes.txt` entity.
 ///
@@ -372,7 +471,7 @@ impl_object!(Operator, "operators");
 /// of the stop locations visited by this route and the name of the route. Two
 /// very similar routes may generate the same OnestopID value, in which more
 /// than one route entry will be returned when searching by OnestopID.
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
 pub struct Route {
     /// Unique integer ID.
     pub id: u64,
@@ -381,7 +480,7 @@ pub struct Route {
     /// GTFS `route_id`.
     pub route_id: Option<String>,
     /// GTFS `route_type`.
-    pub route_type: Option<u64>,
+    pub route_type: Option<RouteType>,
     /// GTFS `route_short_name`.
     pub route_short_name: Option<String>,
     /// GTFS `route_long_name`.
@@ -397,11 +496,21 @@ pub struct Route {
     /// A subset of fields for this route's feed version.
     pub feed_version: Option<partial::FeedVersion>,
     /// An array of all stops visited by this route.
-    #[serde(flatten)]
-    pub route_stops: Option<Vec<Stop>>,
+    pub route_stops: Option<Vec<RouteStop>>,
 }
 
-impl_object!(Route, "routes");
+impl_object!(Route, "routes", query::RouteQuery, {
+    const ACCEPTS_GEO: bool = true;
+});
+
+/// A stop visited by a route.
+///
+/// See also: [`Route`]
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct RouteStop {
+    /// A subset of fields for the stop.
+    pub stop: partial::Stop,
+}
 
 /// Representation of a GTFS `stops.txt` entity.
 ///
@@ -418,7 +527,7 @@ impl_object!(Route, "routes");
 /// As with routes, the OnestopID values for stops are automatically generated.
 /// Two very similar stops may both generate the same OnestopID, in which case a
 /// query for that OnestopID will return two or more stop entries.
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
 pub struct Stop {
     /// Unique integer ID.
     pub id: u64,
@@ -439,9 +548,9 @@ pub struct Stop {
     /// GTFS `zone_id`.
     pub zone_id: Option<String>,
     /// GTFS `wheelchair_boarding`.
-    pub wheelchair_boarding: Option<u64>,
+    pub wheelchair_boarding: Option<Accessibility>,
     /// GTFS `location_type`.
-    pub location_type: Option<u64>,
+    pub location_type: Option<LocationType>,
     // /// A subset of fields for this stop's feed version.
     pub feed_version: HashMap<String, Value>,
     /// GTFS `level`.
@@ -453,10 +562,12 @@ pub struct Stop {
     pub geometry: Geometry<(f64, f64)>,
 }
 
-impl_object!(Stop, "stops");
+impl_object!(Stop, "stops", query::StopQuery, {
+    const ACCEPTS_GEO: bool = true;
+});
 
 /// GTFS level.
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
 pub struct GTFSLevel {
     /// GTFS level_id.
     pub level_id: String,
@@ -473,7 +584,7 @@ pub struct GTFSLevel {
 ///
 /// Includes the same basic structure and fields as the GTFS equivalent, with
 /// some additional metadata.
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
 pub struct Trip {
     /// Unique integer ID.
     pub id: u64,
@@ -488,9 +599,9 @@ pub struct Trip {
     /// GTFS `block_id`.
     pub block_id: Option<String>,
     /// GTFS `wheelchair_accessible`.
-    pub wheelchair_accessible: Option<u64>,
+    pub wheelchair_accessible: Option<Accessibility>,
     /// GTFS `bikes_allowed`.
-    pub bikes_allowed: Option<u64>,
+    pub bikes_allowed: Option<Accessibility>,
     /// Pattern of stops for this trip; values are unique within the feed
     /// version.
     pub stop_pattern_id: Option<u64>,
@@ -509,6 +620,8 @@ pub struct Trip {
 }
 
 impl TransitlandObject<u64> for Trip {
+    type Query = query::TripQuery;
+
     fn query_path(route_key: u64) -> String {
         format!("routes/{}/trips", route_key)
     }
@@ -521,22 +634,22 @@ impl TransitlandObject<u64> for Trip {
 /// Modified GTFS `stop_time` entities.
 ///
 /// See also: [`Trip`]
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
 pub struct StopTime {
-    /// Arrival time, in seconds since midnight.
-    pub arrival_time: u64,
-    /// Departure time, in seconds since midnight.
-    pub departure_time: u64,
+    /// GTFS `arrival_time`.
+    pub arrival_time: ServiceTime,
+    /// GTFS `departure_time`.
+    pub departure_time: ServiceTime,
     /// GTFS `stop_sequence`.
     pub stop_sequence: u64,
     /// GTFS `stop_headsign`.
     pub stop_headsign: String,
     /// GTFS `pickup_type`.
-    pub pickup_type: u64,
+    pub pickup_type: PickupDropOffType,
     /// GTFS `drop_off_type`.
-    pub drop_off_type: u64,
+    pub drop_off_type: PickupDropOffType,
     /// GTFS `timepoint`.
-    pub timepoint: u64,
+    pub timepoint: Timepoint,
     /// GTFS `shape_dist_traveled`.
     pub shape_dist_traveled: f64,
     /// Non-zero if interpolated time values were set during import.
@@ -544,10 +657,60 @@ pub struct StopTime {
                            // TODO stop subset?
 }
 
+/// A departure of a trip from a stop, with realtime estimates when available.
+///
+/// Departures are returned for each stop by
+/// [`DeparturesRequest`](crate::DeparturesRequest).
+///
+/// See also: [`StopDepartures`]
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct Departure {
+    /// The scheduled stop time of this departure.
+    #[serde(flatten)]
+    pub stop_time: StopTime,
+    /// Date of the service day this departure belongs to.
+    pub service_date: NaiveDate,
+    /// Scheduled and estimated arrival at the stop.
+    pub arrival: Option<StopTimeEvent>,
+    /// Scheduled and estimated departure from the stop.
+    pub departure: Option<StopTimeEvent>,
+    /// A subset of fields for the trip, including its route.
+    pub trip: partial::Trip,
+}
+
+/// Scheduled and realtime-estimated time of an arrival or departure.
+///
+/// See also: [`Departure`]
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct StopTimeEvent {
+    /// Scheduled time, in local time.
+    pub scheduled: Option<ServiceTime>,
+    /// Estimated time from realtime data, in local time.
+    pub estimated: Option<ServiceTime>,
+    /// Estimated time from realtime data, in UTC.
+    pub estimated_utc: Option<DateTime<Utc>>,
+    /// Delay relative to the schedule, in seconds.
+    pub delay: Option<i64>,
+    /// Uncertainty of the estimate, in seconds.
+    pub uncertainty: Option<i64>,
+}
+
+/// A stop along with its upcoming departures.
+///
+/// See also: [`Departure`]
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct StopDepartures {
+    /// A subset of fields for the stop.
+    #[serde(flatten)]
+    pub stop: partial::Stop,
+    /// Departures from this stop, in order of departure time.
+    pub departures: Vec<Departure>,
+}
+
 /// Shape for a trip.
 ///
 /// See also: [`Trip`]
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
 pub struct Shape {
     /// GTFS `shape_id`.
     pub shape_id: String,
@@ -560,7 +723,7 @@ pub struct Shape {
 /// GTFS `calendar` and `calendar_dates` entities combined.
 ///
 /// See also: [`Trip`]
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
 pub struct Calendar {
     /// GTFS `service_id`.
     pub service_id: Option<String>,
@@ -593,14 +756,14 @@ pub struct Calendar {
 /// A single GTFS `frequencies` entity.
 ///
 /// See also: [`Trip`]
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
 pub struct Frequency {
-    /// When this trip begins repeating, in seconds.
-    pub start_time: u64,
-    /// When this trip stops repeating, in seconds.
-    pub end_time: u64,
+    /// When this trip begins repeating.
+    pub start_time: ServiceTime,
+    /// When this trip stops repeating.
+    pub end_time: ServiceTime,
     /// GTFS `headway_secs`.
     pub headway_secs: u64,
     /// GTFS `exact_times`.
-    pub exact_times: u64,
+    pub exact_times: ExactTimes,
 }
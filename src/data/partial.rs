@@ -2,12 +2,12 @@
 
 use std::collections::HashMap;
 
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 
-use super::{Spec, Place};
+use super::{Geometry, Place, Spec};
 
 /// See [`FeedVersion`](super::FeedVersion).
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
 pub struct FeedVersion {
     pub id: Option<u64>,
     pub sha1: String,
@@ -18,7 +18,7 @@ pub struct FeedVersion {
 }
 
 /// See [`Feed`](super::Feed).
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
 pub struct Feed {
     pub name: Option<String>,
     pub onestop_id: String,
@@ -26,7 +26,7 @@ pub struct Feed {
 }
 
 /// See [`Operator`](super::Operator).
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
 pub struct Operator {
     pub onestop_id: String,
     pub name: String,
@@ -36,7 +36,7 @@ pub struct Operator {
 }
 
 /// See [`Route`](super::Route).
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
 pub struct Route {
     pub id: u64,
     pub route_id: String,
@@ -45,10 +45,34 @@ pub struct Route {
 }
 
 /// See [`Agency`](super::Agency).
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
 pub struct Agency {
     pub id: u64,
     pub agency_id: Option<String>,
     pub agency_name: Option<String>,
     pub places: Option<Vec<Place>>,
 }
+
+/// See [`Stop`](super::Stop).
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct Stop {
+    pub id: u64,
+    pub onestop_id: Option<String>,
+    pub stop_id: Option<String>,
+    pub stop_name: Option<String>,
+    pub stop_code: Option<String>,
+    pub stop_timezone: Option<String>,
+    pub geometry: Option<Geometry<(f64, f64)>>,
+}
+
+/// See [`Trip`](super::Trip).
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct Trip {
+    pub id: u64,
+    pub trip_id: Option<String>,
+    pub trip_headsign: Option<String>,
+    pub trip_short_name: Option<String>,
+    pub direction_id: Option<u64>,
+    pub block_id: Option<String>,
+    pub route: Option<Route>,
+}
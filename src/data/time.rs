@@ -0,0 +1,224 @@
+//! Times of day within a GTFS service day.
+
+use std::{
+    fmt,
+    ops::{Add, AddAssign, Sub, SubAssign},
+    str::FromStr,
+    time::Duration,
+};
+
+use chrono::{DateTime, NaiveDate, NaiveTime, TimeZone, Timelike};
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::Error;
+
+/// A time within a service day, as used by GTFS `arrival_time`,
+/// `departure_time`, `start_time` and `end_time`.
+///
+/// Service times are measured from "noon minus 12h" on the service date,
+/// which is midnight except on days when daylight saving time begins or ends.
+/// Trips running past midnight are given times past `24:00:00` on the same
+/// service date, so a service time may be greater than a day.
+///
+/// Service times are written as `HH:MM:SS` (or `H:MM:SS`), and deserialize
+/// from either that format or a number of seconds.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct ServiceTime(u32);
+
+impl ServiceTime {
+    /// The start of the service day.
+    pub const MIDNIGHT: ServiceTime = ServiceTime(0);
+
+    /// Returns `None` if `minutes` or `seconds` are out of range.
+    pub fn new(hours: u32, minutes: u32, seconds: u32) -> Option<Self> {
+        if minutes >= 60 || seconds >= 60 {
+            return None;
+        }
+        hours
+            .checked_mul(3600)
+            .and_then(|hours| hours.checked_add(minutes * 60 + seconds))
+            .map(ServiceTime)
+    }
+
+    pub fn from_secs(seconds: u32) -> Self {
+        ServiceTime(seconds)
+    }
+
+    /// Seconds since the start of the service day.
+    pub fn as_secs(&self) -> u32 {
+        self.0
+    }
+
+    /// Hours since the start of the service day, which may be 24 or more.
+    pub fn hour(&self) -> u32 {
+        self.0 / 3600
+    }
+
+    pub fn minute(&self) -> u32 {
+        self.0 / 60 % 60
+    }
+
+    pub fn second(&self) -> u32 {
+        self.0 % 60
+    }
+
+    /// Whether this time falls on the day after the service date.
+    pub fn is_next_day(&self) -> bool {
+        self.0 >= 24 * 3600
+    }
+
+    /// The time of day on the clock, ignoring which day it falls on.
+    ///
+    /// Around daylight saving time transitions, this may differ from the
+    /// local time returned by [`on`](Self::on).
+    pub fn time_of_day(&self) -> NaiveTime {
+        NaiveTime::from_num_seconds_from_midnight_opt(self.0 % (24 * 3600), 0)
+            .expect("seconds are within a day")
+    }
+
+    /// The instant of this time on the given service date, in the timezone
+    /// of the agency or stop.
+    ///
+    /// Returns `None` if noon on the service date does not exist in the
+    /// timezone.
+    pub fn on<Tz: TimeZone>(&self, service_date: NaiveDate, timezone: &Tz) -> Option<DateTime<Tz>> {
+        let noon = service_date.and_hms_opt(12, 0, 0)?;
+        let noon = timezone.from_local_datetime(&noon).earliest()?;
+        Some(noon - chrono::Duration::hours(12) + chrono::Duration::seconds(self.0.into()))
+    }
+
+    pub fn checked_add(self, duration: Duration) -> Option<Self> {
+        u32::try_from(duration.as_secs())
+            .ok()
+            .and_then(|seconds| self.0.checked_add(seconds))
+            .map(ServiceTime)
+    }
+
+    /// Returns `None` if the result would be before the start of the service
+    /// day.
+    pub fn checked_sub(self, duration: Duration) -> Option<Self> {
+        u32::try_from(duration.as_secs())
+            .ok()
+            .and_then(|seconds| self.0.checked_sub(seconds))
+            .map(ServiceTime)
+    }
+
+    /// The signed duration from `other` to this time, such as the delay of an
+    /// estimated time relative to the scheduled time.
+    pub fn signed_duration_since(self, other: ServiceTime) -> chrono::Duration {
+        chrono::Duration::seconds(i64::from(self.0) - i64::from(other.0))
+    }
+}
+
+impl Add<Duration> for ServiceTime {
+    type Output = ServiceTime;
+
+    fn add(self, duration: Duration) -> Self::Output {
+        self.checked_add(duration)
+            .expect("overflow when adding duration to service time")
+    }
+}
+
+impl AddAssign<Duration> for ServiceTime {
+    fn add_assign(&mut self, duration: Duration) {
+        *self = *self + duration;
+    }
+}
+
+impl Sub<Duration> for ServiceTime {
+    type Output = ServiceTime;
+
+    fn sub(self, duration: Duration) -> Self::Output {
+        self.checked_sub(duration)
+            .expect("overflow when subtracting duration from service time")
+    }
+}
+
+impl SubAssign<Duration> for ServiceTime {
+    fn sub_assign(&mut self, duration: Duration) {
+        *self = *self - duration;
+    }
+}
+
+impl Sub for ServiceTime {
+    type Output = chrono::Duration;
+
+    fn sub(self, other: ServiceTime) -> Self::Output {
+        self.signed_duration_since(other)
+    }
+}
+
+impl From<NaiveTime> for ServiceTime {
+    fn from(time: NaiveTime) -> Self {
+        ServiceTime(time.num_seconds_from_midnight())
+    }
+}
+
+impl FromStr for ServiceTime {
+    type Err = Error;
+
+    fn from_str(time: &str) -> Result<Self, Self::Err> {
+        let invalid = || Error::InvalidArgument(format!("invalid GTFS time `{}`", time));
+        let mut parts = time.trim().splitn(3, ':');
+        let mut next = || -> Result<u32, Error> {
+            parts
+                .next()
+                .filter(|part| !part.is_empty() && part.bytes().all(|b| b.is_ascii_digit()))
+                .and_then(|part| part.parse().ok())
+                .ok_or_else(invalid)
+        };
+        let (hours, minutes, seconds) = (next()?, next()?, next()?);
+        ServiceTime::new(hours, minutes, seconds).ok_or_else(invalid)
+    }
+}
+
+impl fmt::Display for ServiceTime {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{:02}:{:02}:{:02}",
+            self.hour(),
+            self.minute(),
+            self.second()
+        )
+    }
+}
+
+impl Serialize for ServiceTime {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for ServiceTime {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct Visitor;
+
+        impl<'de> de::Visitor<'de> for Visitor {
+            type Value = ServiceTime;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("a time in HH:MM:SS or a number of seconds")
+            }
+
+            fn visit_u64<E: de::Error>(self, seconds: u64) -> Result<Self::Value, E> {
+                u32::try_from(seconds)
+                    .map(ServiceTime)
+                    .map_err(|_| E::invalid_value(de::Unexpected::Unsigned(seconds), &self))
+            }
+
+            fn visit_i64<E: de::Error>(self, seconds: i64) -> Result<Self::Value, E> {
+                u32::try_from(seconds)
+                    .map(ServiceTime)
+                    .map_err(|_| E::invalid_value(de::Unexpected::Signed(seconds), &self))
+            }
+
+            fn visit_str<E: de::Error>(self, time: &str) -> Result<Self::Value, E> {
+                time.parse()
+                    .map_err(|_| E::custom(format!("invalid GTFS time `{}`", time)))
+            }
+        }
+
+        deserializer.deserialize_any(Visitor)
+    }
+}
@@ -0,0 +1,128 @@
+//! Timezones of agencies and stops, using [`chrono_tz`], and fetch times in
+//! UTC.
+
+use chrono::{DateTime, NaiveDate, NaiveDateTime, Utc};
+use chrono_tz::Tz;
+
+use super::{partial, Agency, FeedState, FeedVersion, ServiceTime, Stop};
+use crate::{Error, Result};
+
+/// Parse an IANA timezone name, such as `America/Los_Angeles`.
+///
+/// Missing and empty names are `None`.
+fn parse_timezone(name: Option<&str>) -> Result<Option<Tz>> {
+    match name.map(str::trim).filter(|name| !name.is_empty()) {
+        Some(name) => name
+            .parse()
+            .map(Some)
+            .map_err(|_| Error::UnknownTimezone(name.to_owned())),
+        None => Ok(None),
+    }
+}
+
+/// The timezone in which times at a stop are given: the stop's own
+/// timezone if it has one, otherwise its agency's.
+///
+/// Fails with [`Error::UnknownTimezone`] if either name is not a known IANA
+/// timezone.
+pub fn resolve_timezone(
+    stop_timezone: Option<&str>,
+    agency_timezone: Option<&str>,
+) -> Result<Option<Tz>> {
+    match parse_timezone(stop_timezone)? {
+        Some(timezone) => Ok(Some(timezone)),
+        None => parse_timezone(agency_timezone),
+    }
+}
+
+impl Agency {
+    /// GTFS `agency_timezone`, parsed.
+    pub fn timezone(&self) -> Result<Option<Tz>> {
+        parse_timezone(self.agency_timezone.as_deref())
+    }
+}
+
+impl Stop {
+    /// GTFS `stop_timezone`, parsed.
+    ///
+    /// Stops without their own timezone use their agency's; see
+    /// [`Stop::resolve_timezone`].
+    pub fn timezone(&self) -> Result<Option<Tz>> {
+        parse_timezone(self.stop_timezone.as_deref())
+    }
+
+    /// The timezone of this stop, falling back to that of the agency serving
+    /// it.
+    pub fn resolve_timezone(&self, agency: &Agency) -> Result<Option<Tz>> {
+        resolve_timezone(
+            self.stop_timezone.as_deref(),
+            agency.agency_timezone.as_deref(),
+        )
+    }
+
+    /// The instant of a service time at this stop, such as a stop time
+    /// of a trip serving it.
+    ///
+    /// Returns `None` if neither the stop nor the agency has a timezone.
+    pub fn local_time(
+        &self,
+        agency: &Agency,
+        service_date: NaiveDate,
+        time: ServiceTime,
+    ) -> Result<Option<DateTime<Tz>>> {
+        Ok(self
+            .resolve_timezone(agency)?
+            .and_then(|timezone| time.on(service_date, &timezone)))
+    }
+}
+
+impl partial::Stop {
+    /// See [`Stop::timezone`].
+    pub fn timezone(&self) -> Result<Option<Tz>> {
+        parse_timezone(self.stop_timezone.as_deref())
+    }
+
+    /// See [`Stop::resolve_timezone`].
+    pub fn resolve_timezone(&self, agency: &Agency) -> Result<Option<Tz>> {
+        resolve_timezone(
+            self.stop_timezone.as_deref(),
+            agency.agency_timezone.as_deref(),
+        )
+    }
+}
+
+impl FeedState {
+    /// Time of last attempted fetch, which Transitland gives in UTC.
+    pub fn last_fetched_at_utc(&self) -> Option<DateTime<Utc>> {
+        self.last_fetched_at.map(|time| time.and_utc())
+    }
+
+    /// Time of last successful fetch, which Transitland gives in UTC.
+    pub fn last_successful_fetch_at_utc(&self) -> Option<DateTime<Utc>> {
+        self.last_successful_fetch_at.map(|time| time.and_utc())
+    }
+}
+
+impl FeedVersion {
+    /// Time when the file was fetched, which Transitland gives in UTC.
+    pub fn fetched_at_utc(&self) -> DateTime<Utc> {
+        self.fetched_at.and_utc()
+    }
+}
+
+impl partial::FeedVersion {
+    /// See [`FeedVersion::fetched_at_utc`].
+    ///
+    /// Returns `None` if the time is neither in RFC 3339 format nor without an
+    /// offset, in which case it is in UTC.
+    pub fn fetched_at_utc(&self) -> Option<DateTime<Utc>> {
+        DateTime::parse_from_rfc3339(&self.fetched_at)
+            .map(|time| time.with_timezone(&Utc))
+            .or_else(|_| {
+                self.fetched_at
+                    .parse::<NaiveDateTime>()
+                    .map(|time| time.and_utc())
+            })
+            .ok()
+    }
+}
@@ -0,0 +1,220 @@
+use std::time::Duration;
+
+use chrono::NaiveDate;
+
+use crate::{
+    api::encode_path_segment,
+    data::{ServiceTime, StopDepartures},
+    Client, Request, Result, SearchResponse,
+};
+
+/// A request for the departures from a stop.
+///
+/// By default, departures over the next hour of the current service day are
+/// returned.
+///
+/// See also: [`Client::departures`], [`Request::departures`]
+#[derive(Debug, Clone)]
+pub struct DeparturesRequest {
+    client: Client,
+    base_url: Option<String>,
+    next: Option<Duration>,
+    start_time: Option<ServiceTime>,
+    end_time: Option<ServiceTime>,
+    date: Option<NaiveDate>,
+    include_geometry: bool,
+}
+
+impl DeparturesRequest {
+    pub fn new(client: Client) -> Self {
+        DeparturesRequest {
+            client,
+            base_url: None,
+            next: None,
+            start_time: None,
+            end_time: None,
+            date: None,
+            include_geometry: false,
+        }
+    }
+
+    /// Fetch the departures from the stop with the given key (an integer ID
+    /// or OnestopID).
+    pub async fn get(&self, stop_key: &str) -> Result<Vec<StopDepartures>> {
+        let path = format!("stops/{}/departures", encode_path_segment(stop_key));
+
+        let mut params = vec![("include_geometry", self.include_geometry.to_string())];
+        if let Some(next) = self.next {
+            params.push(("next", next.as_secs().to_string()));
+        }
+        if let Some(start_time) = self.start_time {
+            params.push(("start_time", start_time.to_string()));
+        }
+        if let Some(end_time) = self.end_time {
+            params.push(("end_time", end_time.to_string()));
+        }
+        if let Some(date) = self.date {
+            params.push(("date", date.format("%Y-%m-%d").to_string()));
+        }
+
+        let body = self
+            .client
+            .fetch(
+                self.base_url.as_deref(),
+                &path,
+                &params,
+                Duration::ZERO,
+                false,
+            )
+            .await?;
+        let response = SearchResponse::<StopDepartures>::decode(&body)?;
+        Ok(response.into_values())
+    }
+
+    /// Only return departures within the given duration from now.
+    pub fn with_next(mut self, next: Duration) -> Self {
+        self.next = Some(next);
+        self
+    }
+
+    /// Only return departures at or after the given time of the service day.
+    ///
+    /// Accepts a [`NaiveTime`](chrono::NaiveTime), or a [`ServiceTime`] past
+    /// `24:00:00` for departures after midnight.
+    pub fn with_start_time(mut self, start_time: impl Into<ServiceTime>) -> Self {
+        self.start_time = Some(start_time.into());
+        self
+    }
+
+    /// Only return departures at or before the given time of the service day.
+    ///
+    /// Accepts a [`NaiveTime`](chrono::NaiveTime), or a [`ServiceTime`] past
+    /// `24:00:00` for departures after midnight.
+    pub fn with_end_time(mut self, end_time: impl Into<ServiceTime>) -> Self {
+        self.end_time = Some(end_time.into());
+        self
+    }
+
+    /// Return departures for the given service date instead of today.
+    pub fn with_date(mut self, date: NaiveDate) -> Self {
+        self.date = Some(date);
+        self
+    }
+
+    /// Include the geometry of each stop in the response.
+    pub fn with_include_geometry(mut self, include_geometry: bool) -> Self {
+        self.include_geometry = include_geometry;
+        self
+    }
+}
+
+impl Request {
+    /// Start building a request for the departures from a stop, sent to the
+    /// same base URL as this request.
+    pub fn departures(&self) -> DeparturesRequest {
+        DeparturesRequest {
+            base_url: self.base_url().map(str::to_owned),
+            ..DeparturesRequest::new(self.client().clone())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::NaiveTime;
+    use serde_json::json;
+
+    use super::*;
+    use crate::test_server::{response, test_client, TestServer};
+
+    #[tokio::test]
+    async fn get() {
+        let body = json!({
+            "stops": [{
+                "id": 12,
+                "onestop_id": "s-9q9p1wxf72-macarthur",
+                "stop_id": "MCAR",
+                "stop_name": "MacArthur",
+                "geometry": { "type": "Point", "coordinates": [-122.267, 37.829] },
+                "departures": [{
+                    "arrival_time": "24:40:00",
+                    "departure_time": "24:40:30",
+                    "stop_sequence": 12,
+                    "stop_headsign": "Antioch",
+                    "pickup_type": 0,
+                    "drop_off_type": 0,
+                    "timepoint": 1,
+                    "shape_dist_traveled": 20.5,
+                    "interpolated": 0,
+                    "service_date": "2023-03-14",
+                    "arrival": { "scheduled": "24:40:00", "estimated": "24:41:30", "delay": 90 },
+                    "departure": null,
+                    "trip": {
+                        "id": 100,
+                        "trip_id": "1234",
+                        "trip_headsign": "Antioch",
+                        "trip_short_name": null,
+                        "direction_id": 0,
+                        "block_id": null,
+                        "route": null
+                    }
+                }]
+            }]
+        });
+        let server = TestServer::start(vec![response(
+            200,
+            &[("Content-Type", "application/json")],
+            body.to_string(),
+        )])
+        .await;
+        let client = test_client(server.url());
+
+        let stops = client
+            .departures()
+            .with_start_time(NaiveTime::from_hms_opt(23, 30, 0).unwrap())
+            .with_end_time(ServiceTime::new(25, 15, 0).unwrap())
+            .with_date(NaiveDate::from_ymd_opt(2023, 3, 14).unwrap())
+            .with_next(Duration::from_secs(3600))
+            .with_include_geometry(true)
+            .get("s-9q9p1wxf72-macarthur")
+            .await
+            .unwrap();
+
+        let requests = server.requests();
+        assert!(requests[0].starts_with(
+            "get /stops/s-9q9p1wxf72-macarthur/departures?apikey=secret&include_geometry=true\
+             &next=3600&start_time=23%3a30%3a00&end_time=25%3a15%3a00&date=2023-03-14 "
+        ));
+
+        assert_eq!(stops.len(), 1);
+        assert_eq!(stops[0].stop.stop_id.as_deref(), Some("MCAR"));
+        let departure = &stops[0].departures[0];
+        assert_eq!(
+            departure.stop_time.departure_time,
+            ServiceTime::new(24, 40, 30).unwrap()
+        );
+        let arrival = departure.arrival.as_ref().unwrap();
+        assert_eq!(arrival.estimated, ServiceTime::new(24, 41, 30));
+        assert_eq!(arrival.delay, Some(90));
+        assert_eq!(departure.trip.trip_id.as_deref(), Some("1234"));
+    }
+
+    #[tokio::test]
+    async fn request_base_url() {
+        let server = TestServer::start(vec![response(200, &[], "{\"stops\": []}")]).await;
+        // nothing listens on the client's own base URL
+        let client = test_client(TestServer::start(vec![]).await.url());
+
+        let stops = client
+            .request()
+            .with_base_url(server.url())
+            .departures()
+            .get("s-9q9/ mac")
+            .await
+            .unwrap();
+        assert!(stops.is_empty());
+
+        let requests = server.requests();
+        assert!(requests[0].starts_with("get /stops/s-9q9%2f%20mac/departures?"));
+    }
+}
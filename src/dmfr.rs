@@ -0,0 +1,365 @@
+//! Reading and writing DMFR feed registries.
+//!
+//! The [Distributed Mobility Feed Registry](https://github.com/transitland/distributed-mobility-feed-registry)
+//! format lists feeds and the operators they belong to in `.dmfr.json` files,
+//! as in the [Transitland Atlas](https://github.com/transitland/transitland-atlas)
+//! which Transitland itself is built from. A [`Registry`] can be read from
+//! such a file, checked with [`Registry::validate`], combined with others with
+//! [`Registry::merge`], and written back out.
+//!
+//! Feeds in a registry share the [`Urls`], [`License`] and [`Authorization`]
+//! types of [`data::Feed`](crate::data::Feed), but are identified by their
+//! OnestopID alone.
+
+use std::{collections::BTreeMap, fs, path::Path};
+
+use reqwest::Url;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    data::{Authorization, AuthorizationType, License, Spec, Urls},
+    error::decode,
+    Result,
+};
+
+/// Schema referenced by registries written by this crate, unless they
+/// already reference one.
+const DEFAULT_SCHEMA: &str = "https://dmfr.transit.land/json-schema/dmfr.schema-v0.5.0.json";
+
+/// The contents of a DMFR file.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+pub struct Registry {
+    /// URL of the JSON schema the registry conforms to.
+    #[serde(rename = "$schema", skip_serializing_if = "Option::is_none")]
+    pub schema: Option<String>,
+    #[serde(default)]
+    pub feeds: Vec<Feed>,
+    /// Operators which are not nested within a feed, typically because they
+    /// are associated with several.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub operators: Vec<Operator>,
+    /// SPDX identifier of the license of the registry itself.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub license_spdx_identifier: Option<String>,
+}
+
+/// A feed listed in a registry.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct Feed {
+    /// OnestopID of the feed.
+    pub id: String,
+    pub spec: Spec,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    /// Feeds that share the same namespace can be combined without needing to
+    /// rewrite entity IDs.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub feed_namespace_id: Option<String>,
+    /// OnestopIDs of related feeds, such as the static feed of a realtime
+    /// feed.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub associated_feeds: Vec<String>,
+    /// OnestopIDs of feeds this feed replaces.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub supersedes_ids: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub languages: Vec<String>,
+    pub urls: Urls,
+    #[serde(default, skip_serializing_if = "is_default_license")]
+    pub license: License,
+    #[serde(default, skip_serializing_if = "is_default_authorization")]
+    pub authorization: Authorization,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub tags: BTreeMap<String, String>,
+    /// Operators of the feed. Unless listed explicitly, these are associated
+    /// with this feed.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub operators: Vec<Operator>,
+}
+
+/// An operator listed in a registry.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct Operator {
+    pub onestop_id: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub short_name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub website: Option<String>,
+    /// Feeds and agencies within them which make up this operator.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub associated_feeds: Vec<AssociatedFeed>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub tags: BTreeMap<String, String>,
+}
+
+/// A feed, or an agency within a feed, associated with an operator.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct AssociatedFeed {
+    /// OnestopID of the feed. May be omitted for operators nested within the
+    /// feed.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub feed_onestop_id: Option<String>,
+    /// `agency_id` of the agency within the feed, if it has more than one.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub gtfs_agency_id: Option<String>,
+}
+
+/// A problem found by [`Registry::validate`].
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+#[error("{path}: {message}")]
+pub struct ValidationError {
+    /// Location of the problem within the registry, such as
+    /// `feeds[2].urls`.
+    pub path: String,
+    pub message: String,
+}
+
+impl Registry {
+    /// Parse a registry from JSON.
+    pub fn from_slice(json: &[u8]) -> Result<Self> {
+        decode(json)
+    }
+
+    /// Read a registry from a `.dmfr.json` file.
+    pub fn read(path: impl AsRef<Path>) -> Result<Self> {
+        Registry::from_slice(&fs::read(path)?)
+    }
+
+    /// Format the registry as pretty-printed JSON.
+    pub fn to_json(&self) -> String {
+        let mut registry = self.clone();
+        registry
+            .schema
+            .get_or_insert_with(|| DEFAULT_SCHEMA.to_owned());
+        let mut json =
+            serde_json::to_string_pretty(&registry).expect("registries are always serializable");
+        json.push('\n');
+        json
+    }
+
+    /// Write the registry to a `.dmfr.json` file.
+    pub fn write(&self, path: impl AsRef<Path>) -> Result<()> {
+        fs::write(path, self.to_json())?;
+        Ok(())
+    }
+
+    /// All operators in the registry, whether nested within a feed or not,
+    /// along with the feed they are nested within.
+    pub fn all_operators(&self) -> impl Iterator<Item = (Option<&Feed>, &Operator)> {
+        let nested = self.feeds.iter().flat_map(|feed| {
+            feed.operators
+                .iter()
+                .map(move |operator| (Some(feed), operator))
+        });
+        let top_level = self.operators.iter().map(|operator| (None, operator));
+        nested.chain(top_level)
+    }
+
+    /// Check the registry against the rules of the DMFR format.
+    ///
+    /// References to feeds are checked against the feeds of this registry,
+    /// so registries which refer to each other should be merged first.
+    pub fn validate(&self) -> std::result::Result<(), Vec<ValidationError>> {
+        let mut errors = vec![];
+        let mut error = |path: String, message: String| {
+            errors.push(ValidationError { path, message });
+        };
+
+        let mut feed_ids = BTreeMap::new();
+        for (i, feed) in self.feeds.iter().enumerate() {
+            let path = format!("feeds[{}]", i);
+            check_onestop_id(&feed.id, "f-", &format!("{}.id", path), &mut error);
+            if let Some(other) = feed_ids.insert(feed.id.as_str(), i) {
+                error(
+                    path.clone(),
+                    format!("duplicate of feeds[{}] `{}`", other, feed.id),
+                );
+            }
+            check_feed(feed, &path, &mut error);
+        }
+
+        let mut operator_ids = BTreeMap::new();
+        let operators = self
+            .feeds
+            .iter()
+            .enumerate()
+            .flat_map(|(i, feed)| {
+                feed.operators.iter().enumerate().map(move |(j, operator)| {
+                    (format!("feeds[{}].operators[{}]", i, j), true, operator)
+                })
+            })
+            .chain(
+                self.operators
+                    .iter()
+                    .enumerate()
+                    .map(|(i, operator)| (format!("operators[{}]", i), false, operator)),
+            );
+        for (path, nested, operator) in operators {
+            check_onestop_id(
+                &operator.onestop_id,
+                "o-",
+                &format!("{}.onestop_id", path),
+                &mut error,
+            );
+            if let Some(other) = operator_ids.insert(operator.onestop_id.clone(), path.clone()) {
+                error(
+                    path.clone(),
+                    format!("duplicate of {} `{}`", other, operator.onestop_id),
+                );
+            }
+            for (j, associated) in operator.associated_feeds.iter().enumerate() {
+                let path = format!("{}.associated_feeds[{}]", path, j);
+                match &associated.feed_onestop_id {
+                    Some(id) if !feed_ids.contains_key(id.as_str()) => {
+                        error(path, format!("unknown feed `{}`", id))
+                    }
+                    None if !nested => error(
+                        path,
+                        "feed_onestop_id is required outside of a feed".to_owned(),
+                    ),
+                    _ => {}
+                }
+            }
+        }
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+
+    /// Add the feeds and operators of another registry to this one.
+    ///
+    /// Feeds and operators with the same OnestopID as one already in this
+    /// registry replace it in place, so that registries merged later take
+    /// precedence.
+    pub fn merge(&mut self, other: Registry) {
+        for feed in other.feeds {
+            match self
+                .feeds
+                .iter_mut()
+                .find(|existing| existing.id == feed.id)
+            {
+                Some(existing) => *existing = feed,
+                None => self.feeds.push(feed),
+            }
+        }
+        for operator in other.operators {
+            let existing = self
+                .operators
+                .iter_mut()
+                .find(|existing| existing.onestop_id == operator.onestop_id);
+            match existing {
+                Some(existing) => *existing = operator,
+                None => self.operators.push(operator),
+            }
+        }
+        if other.license_spdx_identifier.is_some() {
+            self.license_spdx_identifier = other.license_spdx_identifier;
+        }
+        if self.schema.is_none() {
+            self.schema = other.schema;
+        }
+    }
+}
+
+fn check_onestop_id(id: &str, prefix: &str, path: &str, error: &mut impl FnMut(String, String)) {
+    // OnestopIDs are `<prefix><geohash>-<name>`, though many registered ones
+    // omit the geohash
+    let valid = id
+        .strip_prefix(prefix)
+        .is_some_and(|rest| !rest.is_empty() && !rest.contains(char::is_whitespace));
+    if !valid {
+        error(
+            path.to_owned(),
+            format!(
+                "`{}` is not a valid OnestopID starting with `{}`",
+                id, prefix
+            ),
+        );
+    }
+}
+
+fn check_feed(feed: &Feed, path: &str, error: &mut impl FnMut(String, String)) {
+    let urls = &feed.urls;
+    let optional_urls = [
+        ("static_current", &urls.static_current),
+        ("static_planned", &urls.static_planned),
+        ("static_hypothetical", &urls.static_hypothetical),
+        (
+            "realtime_vehicle_positions",
+            &urls.realtime_vehicle_positions,
+        ),
+        ("realtime_trip_updates", &urls.realtime_trip_updates),
+        ("realtime_alerts", &urls.realtime_alerts),
+        ("gbfs_auto_discovery", &urls.gbfs_auto_discovery),
+    ];
+    let mut all_urls: Vec<(&str, &String)> = optional_urls
+        .into_iter()
+        .filter_map(|(name, url)| Some((name, url.as_ref()?)))
+        .collect();
+    all_urls.extend(
+        urls.static_historic
+            .iter()
+            .map(|url| ("static_historic", url)),
+    );
+
+    for (name, url) in &all_urls {
+        // path segment secrets are inserted in place of `{}`
+        if Url::parse(&url.replace("{}", "secret")).is_err() {
+            error(
+                format!("{}.urls.{}", path, name),
+                format!("`{}` is not a valid URL", url),
+            );
+        }
+    }
+
+    let has_url = match feed.spec {
+        Spec::GTFS => all_urls.iter().any(|(name, _)| name.starts_with("static_")),
+        Spec::GTFSRealtime => all_urls
+            .iter()
+            .any(|(name, _)| name.starts_with("realtime_")),
+        Spec::GBFS => urls.gbfs_auto_discovery.is_some(),
+        Spec::MDS => !all_urls.is_empty(),
+    };
+    if !has_url {
+        error(
+            format!("{}.urls", path),
+            format!("no URLs for a {} feed", feed.spec.as_str()),
+        );
+    }
+
+    let authorization = &feed.authorization;
+    match authorization.auth_type {
+        Some(AuthorizationType::Header) | Some(AuthorizationType::QueryParam)
+            if authorization.param_name.is_none() =>
+        {
+            error(
+                format!("{}.authorization.param_name", path),
+                "required for header and query_param authorization".to_owned(),
+            );
+        }
+        Some(AuthorizationType::PathSegment) => {
+            if let Some((name, _)) = all_urls.iter().find(|(_, url)| !url.contains("{}")) {
+                error(
+                    format!("{}.urls.{}", path, name),
+                    "must contain `{}` for path_segment authorization".to_owned(),
+                );
+            }
+        }
+        _ => {}
+    }
+}
+
+fn is_default_license(license: &License) -> bool {
+    *license == License::default()
+}
+
+fn is_default_authorization(authorization: &Authorization) -> bool {
+    *authorization == Authorization::default()
+}
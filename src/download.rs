@@ -0,0 +1,341 @@
+use std::path::{Path, PathBuf};
+
+use bytes::Bytes;
+use chrono::{DateTime, NaiveDateTime};
+use futures::{stream, Stream};
+use reqwest::Response;
+use sha1::{Digest, Sha1};
+use tokio::{fs, io::AsyncWriteExt};
+
+use crate::{
+    api::encode_path_segment,
+    data::{partial, Feed},
+    Client, Error, Request, Result,
+};
+
+/// A GTFS archive being downloaded.
+///
+/// The archive is hashed as it is read, and checked against the SHA1 of its
+/// feed version once fully downloaded. If the checksum does not match, the
+/// final read fails with [`Error::ChecksumMismatch`].
+///
+/// See also: [`Request::download_feed_version`],
+/// [`Request::download_latest_feed_version`]
+#[derive(Debug)]
+pub struct Download {
+    response: Response,
+    hasher: Sha1,
+    expected_sha1: String,
+}
+
+impl Download {
+    fn new(response: Response, expected_sha1: String) -> Self {
+        Download {
+            response,
+            hasher: Sha1::new(),
+            expected_sha1,
+        }
+    }
+
+    /// SHA1 the archive is expected to have.
+    pub fn expected_sha1(&self) -> &str {
+        &self.expected_sha1
+    }
+
+    /// Size of the archive in bytes, if reported by the server.
+    pub fn content_length(&self) -> Option<u64> {
+        self.response.content_length()
+    }
+
+    /// Read the next chunk of the archive, or `None` once it has been fully
+    /// read and verified.
+    pub async fn chunk(&mut self) -> Result<Option<Bytes>> {
+        match self.response.chunk().await? {
+            Some(chunk) => {
+                self.hasher.update(&chunk);
+                Ok(Some(chunk))
+            }
+            None => {
+                self.verify()?;
+                Ok(None)
+            }
+        }
+    }
+
+    /// Stream the chunks of the archive.
+    pub fn into_stream(self) -> impl Stream<Item = Result<Bytes>> {
+        stream::try_unfold(self, |mut download| async move {
+            Ok(download.chunk().await?.map(|chunk| (chunk, download)))
+        })
+    }
+
+    /// Read the whole archive into memory.
+    pub async fn bytes(mut self) -> Result<Bytes> {
+        let mut bytes = vec![];
+        while let Some(chunk) = self.chunk().await? {
+            bytes.extend_from_slice(&chunk);
+        }
+        Ok(bytes.into())
+    }
+
+    /// Write the archive to a file, returning its SHA1.
+    ///
+    /// The archive is first written next to `path` with a `.part` suffix, and
+    /// only moved into place once verified.
+    pub async fn to_file(mut self, path: impl AsRef<Path>) -> Result<String> {
+        let path = path.as_ref();
+        let mut part_path = PathBuf::from(path);
+        part_path.as_mut_os_string().push(".part");
+
+        let result = async {
+            let mut file = fs::File::create(&part_path).await?;
+            while let Some(chunk) = self.chunk().await? {
+                file.write_all(&chunk).await?;
+            }
+            file.sync_all().await?;
+            fs::rename(&part_path, path).await?;
+            Ok(self.sha1())
+        }
+        .await;
+
+        if result.is_err() {
+            let _ = fs::remove_file(&part_path).await;
+        }
+        result
+    }
+
+    /// SHA1 of the data read so far.
+    fn sha1(&self) -> String {
+        hex::encode(self.hasher.clone().finalize())
+    }
+
+    fn verify(&self) -> Result<()> {
+        let actual = self.sha1();
+        if !self.expected_sha1.eq_ignore_ascii_case(&actual) {
+            return Err(Error::ChecksumMismatch {
+                expected: self.expected_sha1.clone(),
+                actual,
+            });
+        }
+        Ok(())
+    }
+}
+
+impl Request {
+    /// Download the GTFS archive of the feed version with the given SHA1.
+    pub async fn download_feed_version(&self, sha1: &str) -> Result<Download> {
+        let path = format!("feed_versions/{}/download", encode_path_segment(sha1));
+        let response = self.client().fetch_response(self.base_url(), &path).await?;
+        Ok(Download::new(response, sha1.to_owned()))
+    }
+
+    /// Download the GTFS archive of the latest version of a feed, given its
+    /// integer ID or OnestopID.
+    ///
+    /// The feed is looked up first to find the SHA1 of its most recently
+    /// fetched version, which may differ from its active version. If that
+    /// cannot be determined, the archive could not be verified, so this fails
+    /// with [`Error::ChecksumUnavailable`] without downloading it.
+    pub async fn download_latest_feed_version(&self, feed_key: &str) -> Result<Download> {
+        let feed: Option<Feed> = self.clone().with_revalidation(true).get(feed_key).await?;
+        let expected_sha1 = feed
+            .and_then(|feed| latest_sha1(&feed.feed_versions))
+            .ok_or_else(|| Error::ChecksumUnavailable(feed_key.to_owned()))?;
+
+        let path = format!(
+            "feeds/{}/download_latest_feed_version",
+            encode_path_segment(feed_key)
+        );
+        let response = self.client().fetch_response(self.base_url(), &path).await?;
+        Ok(Download::new(response, expected_sha1))
+    }
+}
+
+impl Client {
+    /// Download the GTFS archive of the feed version with the given SHA1.
+    ///
+    /// See also: [`Request::download_feed_version`]
+    pub async fn download_feed_version(&self, sha1: &str) -> Result<Download> {
+        self.request().download_feed_version(sha1).await
+    }
+
+    /// Download the GTFS archive of the latest version of a feed, given its
+    /// integer ID or OnestopID.
+    ///
+    /// See also: [`Request::download_latest_feed_version`]
+    pub async fn download_latest_feed_version(&self, feed_key: &str) -> Result<Download> {
+        self.request().download_latest_feed_version(feed_key).await
+    }
+}
+
+/// SHA1 of the most recently fetched of the given feed versions, ignoring
+/// those whose fetch time cannot be parsed.
+fn latest_sha1(feed_versions: &[partial::FeedVersion]) -> Option<String> {
+    feed_versions
+        .iter()
+        .filter_map(|feed_version| {
+            Some((parse_fetched_at(&feed_version.fetched_at)?, feed_version))
+        })
+        .max_by_key(|(fetched_at, _)| *fetched_at)
+        .map(|(_, feed_version)| feed_version.sha1.clone())
+}
+
+/// Parse a fetch time, which is in UTC if no offset is given.
+fn parse_fetched_at(time: &str) -> Option<NaiveDateTime> {
+    DateTime::parse_from_rfc3339(time)
+        .map(|time| time.naive_utc())
+        .or_else(|_| time.parse())
+        .ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::test_server::{feed_json, response, test_client, TestServer};
+
+    fn feed_version(sha1: &str, fetched_at: &str) -> partial::FeedVersion {
+        partial::FeedVersion {
+            id: None,
+            sha1: sha1.into(),
+            fetched_at: fetched_at.into(),
+            url: None,
+            earliest_calendar_date: None,
+            latest_calendar_date: None,
+        }
+    }
+
+    #[test]
+    fn latest_sha1_by_fetch_time() {
+        let feed_versions = [
+            feed_version("b", "2023-01-02T03:04:05Z"),
+            feed_version("c", "2023-01-03T00:00:00-08:00"),
+            feed_version("a", "2023-01-01T00:00:00"),
+        ];
+        assert_eq!(latest_sha1(&feed_versions).as_deref(), Some("c"));
+        assert_eq!(latest_sha1(&feed_versions[..1]).as_deref(), Some("b"));
+    }
+
+    #[test]
+    fn latest_sha1_unknown() {
+        assert_eq!(latest_sha1(&[]), None);
+        assert_eq!(latest_sha1(&[feed_version("a", "yesterday")]), None);
+
+        // versions with unknown fetch times are skipped
+        let feed_versions = [
+            feed_version("a", "2023-01-01T00:00:00Z"),
+            feed_version("b", "yesterday"),
+        ];
+        assert_eq!(latest_sha1(&feed_versions).as_deref(), Some("a"));
+    }
+
+    /// A request sent to `server`, through a client whose own base URL is
+    /// not listened on.
+    async fn request(server: &TestServer) -> Request {
+        test_client(TestServer::start(vec![]).await.url())
+            .request()
+            .with_base_url(server.url())
+    }
+
+    fn sha1(data: &str) -> String {
+        hex::encode(Sha1::digest(data))
+    }
+
+    fn feed(feed_versions: serde_json::Value) -> Vec<u8> {
+        let body = json!({ "feeds": [feed_json(json!({ "feed_versions": feed_versions }))] });
+        response(
+            200,
+            &[("Content-Type", "application/json")],
+            body.to_string(),
+        )
+    }
+
+    #[tokio::test]
+    async fn download_feed_version() {
+        let server = TestServer::start(vec![
+            response(200, &[], "archive"),
+            response(200, &[], "corrupt"),
+        ])
+        .await;
+        let request = request(&server).await;
+
+        let download = request
+            .download_feed_version(&sha1("archive"))
+            .await
+            .unwrap();
+        assert_eq!(download.expected_sha1(), sha1("archive"));
+        assert_eq!(download.bytes().await.unwrap(), "archive");
+
+        let download = request
+            .download_feed_version(&sha1("archive"))
+            .await
+            .unwrap();
+        assert!(matches!(
+            download.bytes().await,
+            Err(Error::ChecksumMismatch { .. })
+        ));
+
+        let requests = server.requests();
+        let path = format!(
+            "get /feed_versions/{}/download?apikey=secret ",
+            sha1("archive")
+        );
+        assert!(requests[0].starts_with(&path));
+    }
+
+    #[tokio::test]
+    async fn download_feed_version_encoded() {
+        let server = TestServer::start(vec![response(200, &[], "archive")]).await;
+        let download = request(&server)
+            .await
+            .download_feed_version("../feeds")
+            .await
+            .unwrap();
+        assert!(matches!(
+            download.bytes().await,
+            Err(Error::ChecksumMismatch { .. })
+        ));
+        // the key stays a single path segment
+        assert!(server.requests()[0].starts_with("get /feed_versions/..%2ffeeds/download?"));
+    }
+
+    #[tokio::test]
+    async fn download_latest_feed_version() {
+        let server = TestServer::start(vec![
+            feed(json!([
+                { "sha1": sha1("old"), "fetched_at": "2023-01-01T00:00:00Z" },
+                { "sha1": sha1("archive"), "fetched_at": "2023-02-01T00:00:00Z" },
+                { "sha1": sha1("unknown"), "fetched_at": "" }
+            ])),
+            response(200, &[], "archive"),
+        ])
+        .await;
+        let request = request(&server).await;
+
+        let download = request
+            .download_latest_feed_version("f-9q9-bart")
+            .await
+            .unwrap();
+        assert_eq!(download.expected_sha1(), sha1("archive"));
+        assert_eq!(download.bytes().await.unwrap(), "archive");
+
+        let requests = server.requests();
+        assert!(requests[0].starts_with("get /feeds/f-9q9-bart?"));
+        assert!(requests[1]
+            .starts_with("get /feeds/f-9q9-bart/download_latest_feed_version?apikey=secret "));
+    }
+
+    #[tokio::test]
+    async fn download_latest_feed_version_unverifiable() {
+        let server = TestServer::start(vec![feed(json!([]))]).await;
+        let request = request(&server).await;
+
+        assert!(matches!(
+            request.download_latest_feed_version("f-9q9-bart").await,
+            Err(Error::ChecksumUnavailable(feed)) if feed == "f-9q9-bart"
+        ));
+        // the archive is never requested
+        assert_eq!(server.requests().len(), 1);
+    }
+}
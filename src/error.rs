@@ -0,0 +1,426 @@
+use std::{
+    fmt,
+    time::{Duration, SystemTime},
+};
+
+use reqwest::{header::RETRY_AFTER, Response, StatusCode};
+use serde::Deserialize;
+
+/// Number of bytes of context kept on either side of a deserialization
+/// error in [`Error::Deserialize`].
+const SNIPPET_RADIUS: usize = 80;
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+/// Errors produced while talking to the Transitland API or reading feeds.
+///
+/// Some variants only exist when the matching crate feature is enabled, so
+/// this enum is non-exhaustive: matches on it need a wildcard arm.
+#[derive(Debug, thiserror::Error)]
+#[non_exhaustive]
+pub enum Error {
+    /// The API key was missing or rejected (HTTP 401 or 403).
+    #[error("unauthorized: {0}")]
+    Unauthorized(ApiError),
+    /// The requested resource does not exist (HTTP 404).
+    #[error("not found: {0}")]
+    NotFound(ApiError),
+    /// Too many requests were made with this API key (HTTP 429).
+    #[error("rate limited: {error}")]
+    RateLimited {
+        /// How long the server asked us to wait before trying again.
+        retry_after: Option<Duration>,
+        error: ApiError,
+    },
+    /// Any other 4xx response.
+    #[error("client error: {0}")]
+    Client(ApiError),
+    /// Any 5xx response.
+    #[error("server error: {error}")]
+    Server {
+        /// How long the server asked us to wait before trying again, as with
+        /// a 503 from an overloaded server.
+        retry_after: Option<Duration>,
+        error: ApiError,
+    },
+    /// The response body did not match the expected type.
+    #[error("failed to deserialize response at `{path}`: {source}")]
+    Deserialize {
+        /// Path to the value which failed to deserialize, e.g.
+        /// `feeds[0].spec`.
+        path: String,
+        /// Excerpt of the response body around the error.
+        snippet: String,
+        source: serde_json::Error,
+    },
+    /// An argument supplied to a request was invalid.
+    #[error("invalid argument: {0}")]
+    InvalidArgument(String),
+    /// A downloaded file did not have the expected SHA1 checksum.
+    #[error("checksum mismatch: expected SHA1 {expected}, got {actual}")]
+    ChecksumMismatch { expected: String, actual: String },
+    /// The SHA1 checksum of the latest version of the given feed could not
+    /// be determined, so its archive could not be verified.
+    #[error("no checksum available to verify the latest version of feed {0}")]
+    ChecksumUnavailable(String),
+    /// Reading or writing a local file failed.
+    #[error("I/O error: {0}")]
+    Io(#[from] std::io::Error),
+    /// A zip archive could not be read.
+    #[error("zip error: {0}")]
+    Zip(#[from] zip::result::ZipError),
+    /// A CSV file could not be read.
+    #[error("CSV error: {0}")]
+    Csv(#[from] csv::Error),
+    /// A GTFS Realtime message could not be decoded.
+    ///
+    /// Requires the `realtime` feature.
+    #[cfg(feature = "realtime")]
+    #[error("failed to decode protobuf: {0}")]
+    Protobuf(#[from] prost::DecodeError),
+    /// A timezone name was not a known IANA timezone.
+    ///
+    /// Requires the `chrono-tz` feature.
+    #[cfg(feature = "chrono-tz")]
+    #[error("unknown timezone `{0}`")]
+    UnknownTimezone(String),
+    /// A file was missing from a GTFS or GBFS feed.
+    #[error("missing required file `{0}`")]
+    MissingFile(&'static str),
+    /// The request could not be sent or the response could not be read.
+    #[error("transport error: {0}")]
+    Transport(#[from] reqwest::Error),
+}
+
+impl Error {
+    /// HTTP status of the response that caused this error, if any.
+    pub fn status(&self) -> Option<StatusCode> {
+        match self {
+            Error::Unauthorized(error)
+            | Error::NotFound(error)
+            | Error::RateLimited { error, .. }
+            | Error::Client(error)
+            | Error::Server { error, .. } => Some(error.status),
+            Error::Transport(error) => error.status(),
+            _ => None,
+        }
+    }
+
+    /// Build an error from an unsuccessful response, consuming its body.
+    pub(crate) async fn from_response(response: Response) -> Self {
+        let status = response.status();
+        let retry_after = response
+            .headers()
+            .get(RETRY_AFTER)
+            .and_then(|value| value.to_str().ok())
+            .and_then(parse_retry_after);
+        let body = match response.text().await {
+            Ok(body) => body,
+            Err(err) => return Error::Transport(err),
+        };
+        let error = ApiError::new(status, &body);
+
+        match status {
+            StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN => Error::Unauthorized(error),
+            StatusCode::NOT_FOUND => Error::NotFound(error),
+            StatusCode::TOO_MANY_REQUESTS => Error::RateLimited { retry_after, error },
+            status if status.is_server_error() => Error::Server { retry_after, error },
+            _ => Error::Client(error),
+        }
+    }
+}
+
+/// Details of an unsuccessful response from the API.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ApiError {
+    /// HTTP status of the response.
+    pub status: StatusCode,
+    /// Error message provided by the API, if any.
+    pub message: Option<String>,
+}
+
+impl ApiError {
+    fn new(status: StatusCode, body: &str) -> Self {
+        #[derive(Deserialize)]
+        struct Payload {
+            #[serde(alias = "message")]
+            error: String,
+        }
+
+        let message = match serde_json::from_str::<Payload>(body) {
+            Ok(payload) => Some(payload.error),
+            Err(_) => Some(body.trim().to_owned()).filter(|body| !body.is_empty()),
+        };
+        ApiError { status, message }
+    }
+}
+
+impl fmt::Display for ApiError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.status)?;
+        if let Some(message) = &self.message {
+            write!(f, ": {}", message)?;
+        }
+        Ok(())
+    }
+}
+
+/// Parse a `Retry-After` header, given either in seconds or as an HTTP date.
+fn parse_retry_after(value: &str) -> Option<Duration> {
+    let value = value.trim();
+    if let Ok(secs) = value.parse() {
+        return Some(Duration::from_secs(secs));
+    }
+    let date = httpdate::parse_http_date(value).ok()?;
+    Some(
+        date.duration_since(SystemTime::now())
+            .unwrap_or(Duration::ZERO),
+    )
+}
+
+/// Deserialize a JSON response body, reporting where it failed to match.
+pub(crate) fn decode<'de, T: Deserialize<'de>>(body: &'de [u8]) -> Result<T> {
+    decode_within(body, body, "")
+}
+
+/// Deserialize `value`, a slice of the JSON response `body` found at `path`,
+/// reporting where it failed to match relative to the whole body.
+pub(crate) fn decode_within<'de, T: Deserialize<'de>>(
+    body: &[u8],
+    value: &'de [u8],
+    path: &str,
+) -> Result<T> {
+    let deserializer = &mut serde_json::Deserializer::from_slice(value);
+    serde_path_to_error::deserialize(deserializer).map_err(|err| {
+        let path = join_path(path, &err.path().to_string());
+        let source = err.into_inner();
+        // `value` borrows from `body`, so their pointers give its position
+        let start = (value.as_ptr() as usize).saturating_sub(body.as_ptr() as usize);
+        let offset = start + offset(value, source.line(), source.column());
+        Error::Deserialize {
+            path,
+            snippet: snippet(body, offset),
+            source,
+        }
+    })
+}
+
+/// Append a path reported by `serde_path_to_error` to the path of the value
+/// it was reported for.
+fn join_path(prefix: &str, path: &str) -> String {
+    match (prefix, path) {
+        ("", path) => path.to_owned(),
+        (prefix, ".") => prefix.to_owned(),
+        (prefix, path) if path.starts_with('[') => format!("{}{}", prefix, path),
+        (prefix, path) => format!("{}.{}", prefix, path),
+    }
+}
+
+/// Byte offset into `text` of the given (1-indexed) line and column.
+fn offset(text: &[u8], line: usize, column: usize) -> usize {
+    text.split_inclusive(|&byte| byte == b'\n')
+        .take(line.saturating_sub(1))
+        .map(<[u8]>::len)
+        .sum::<usize>()
+        + column.saturating_sub(1)
+}
+
+/// Excerpt of `body` around the given byte offset.
+fn snippet(body: &[u8], offset: usize) -> String {
+    let body = String::from_utf8_lossy(body);
+    let mut start = offset.saturating_sub(SNIPPET_RADIUS).min(body.len());
+    while !body.is_char_boundary(start) {
+        start -= 1;
+    }
+    let mut end = (offset + SNIPPET_RADIUS).min(body.len());
+    while !body.is_char_boundary(end) {
+        end += 1;
+    }
+    body[start..end].to_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::{
+        data::Stop,
+        test_server::{response, test_client, TestServer},
+        SearchResponse,
+    };
+
+    /// The error built from a response with the given status, headers and
+    /// body.
+    async fn error(status: u16, headers: &[(&str, &str)], body: &str) -> Error {
+        let server = TestServer::start(vec![response(status, headers, body)]).await;
+        let response = reqwest::get(server.url()).await.unwrap();
+        Error::from_response(response).await
+    }
+
+    #[tokio::test]
+    async fn status_classification() {
+        for status in [401, 403] {
+            assert!(matches!(
+                error(status, &[], "").await,
+                Error::Unauthorized(_)
+            ));
+        }
+        assert!(matches!(error(404, &[], "").await, Error::NotFound(_)));
+        assert!(matches!(
+            error(429, &[("Retry-After", "30")], "").await,
+            Error::RateLimited {
+                retry_after: Some(retry_after),
+                ..
+            } if retry_after == Duration::from_secs(30)
+        ));
+        assert!(matches!(
+            error(429, &[], "").await,
+            Error::RateLimited {
+                retry_after: None,
+                ..
+            }
+        ));
+        assert!(matches!(
+            error(503, &[("Retry-After", "5")], "").await,
+            Error::Server {
+                retry_after: Some(retry_after),
+                ..
+            } if retry_after == Duration::from_secs(5)
+        ));
+        assert!(matches!(error(500, &[], "").await, Error::Server { .. }));
+        assert!(matches!(error(400, &[], "").await, Error::Client(_)));
+        assert!(matches!(error(422, &[], "").await, Error::Client(_)));
+
+        let error = error(404, &[], "").await;
+        assert_eq!(error.status(), Some(StatusCode::NOT_FOUND));
+    }
+
+    #[tokio::test]
+    async fn api_error_message() {
+        let message = |error: Error| match error {
+            Error::Client(error) => error.message,
+            error => panic!("unexpected error: {:?}", error),
+        };
+        assert_eq!(
+            message(error(400, &[], r#"{"error": "bad search"}"#).await).as_deref(),
+            Some("bad search")
+        );
+        assert_eq!(
+            message(error(400, &[], r#"{"message": "bad limit"}"#).await).as_deref(),
+            Some("bad limit")
+        );
+        // bodies which are not an error payload are kept as they are
+        assert_eq!(
+            message(error(400, &[], " Bad Request\n").await).as_deref(),
+            Some("Bad Request")
+        );
+        assert_eq!(message(error(400, &[], "").await), None);
+    }
+
+    #[test]
+    fn deserialize_path() {
+        #[derive(Debug, Deserialize)]
+        struct Item {
+            #[allow(dead_code)]
+            id: u64,
+        }
+
+        let body = br#"{"items": [{"id": 1}, {"id": "two"}], "meta": null}"#;
+        match SearchResponse::<Item>::decode(body) {
+            Err(Error::Deserialize { path, snippet, .. }) => {
+                assert_eq!(path, "items[1].id");
+                assert_eq!(snippet, String::from_utf8_lossy(body));
+            }
+            result => panic!("unexpected result: {:?}", result),
+        }
+
+        let body = br#"{"items": [], "meta": {"after": "soon"}}"#;
+        match SearchResponse::<Item>::decode(body) {
+            Err(Error::Deserialize { path, .. }) => assert_eq!(path, "meta.after"),
+            result => panic!("unexpected result: {:?}", result),
+        }
+    }
+
+    #[tokio::test]
+    async fn deserialize_path_from_search() {
+        let body = json!({
+            "stops": [{
+                "id": 1,
+                "wheelchair_boarding": "oops",
+                "feed_version": {},
+                "route_stops": [],
+                "geometry": { "type": "Point", "coordinates": [-122.267, 37.829] }
+            }]
+        });
+        let server = TestServer::start(vec![response(200, &[], body.to_string())]).await;
+        match test_client(server.url()).search::<Stop>("bart").await {
+            Err(Error::Deserialize { path, snippet, .. }) => {
+                assert_eq!(path, "stops[0].wheelchair_boarding");
+                assert!(snippet.contains("\"oops\""));
+            }
+            result => panic!("unexpected result: {:?}", result),
+        }
+    }
+
+    #[test]
+    fn snippet_bounds() {
+        let body = format!("{}x{}", "a".repeat(100), "b".repeat(100));
+        let excerpt = snippet(body.as_bytes(), 100);
+        assert_eq!(excerpt.len(), 2 * SNIPPET_RADIUS);
+        assert!(excerpt.starts_with('a') && excerpt.ends_with('b'));
+        assert_eq!(&excerpt[SNIPPET_RADIUS..=SNIPPET_RADIUS], "x");
+
+        // offsets past either end are clamped to the body
+        assert_eq!(snippet(b"short", 2), "short");
+        assert_eq!(snippet(b"short", 1000), "");
+    }
+
+    #[test]
+    fn snippet_multibyte() {
+        // the window edges fall inside the multibyte characters, so are widened
+        // to the nearest character boundaries
+        let body = format!("{}é{}x{}é", "a".repeat(9), "a".repeat(79), "b".repeat(78));
+        let offset = body.find('x').unwrap();
+        assert!(!body.is_char_boundary(offset - SNIPPET_RADIUS));
+        assert!(!body.is_char_boundary(offset + SNIPPET_RADIUS));
+        let excerpt = snippet(body.as_bytes(), offset);
+        assert!(excerpt.starts_with('é'));
+        assert!(excerpt.ends_with('é'));
+        assert_eq!(excerpt.chars().filter(|&c| c == 'x').count(), 1);
+    }
+
+    #[test]
+    fn offset_lines() {
+        let text = b"{\n  \"id\": x\n}";
+        assert_eq!(offset(text, 1, 1), 0);
+        assert_eq!(offset(text, 2, 9), 10);
+        assert_eq!(text[offset(text, 2, 9)], b'x');
+    }
+
+    #[test]
+    fn retry_after_seconds() {
+        assert_eq!(parse_retry_after("120"), Some(Duration::from_secs(120)));
+        assert_eq!(parse_retry_after(" 0 "), Some(Duration::ZERO));
+    }
+
+    #[test]
+    fn retry_after_http_date() {
+        let later = SystemTime::now() + Duration::from_secs(90);
+        let delay = parse_retry_after(&httpdate::fmt_http_date(later)).unwrap();
+        assert!(delay > Duration::from_secs(85) && delay <= Duration::from_secs(90));
+
+        // dates in the past mean the request can be retried immediately
+        assert_eq!(
+            parse_retry_after("Wed, 21 Oct 2015 07:28:00 GMT"),
+            Some(Duration::ZERO)
+        );
+    }
+
+    #[test]
+    fn retry_after_invalid() {
+        assert_eq!(parse_retry_after(""), None);
+        assert_eq!(parse_retry_after("-5"), None);
+        assert_eq!(parse_retry_after("soon"), None);
+    }
+}
@@ -0,0 +1,642 @@
+//! Reading GBFS bikeshare feeds.
+//!
+//! A [`Gbfs`] feed is opened with [`Client::gbfs`] from a
+//! [`Feed`] with the [`GBFS`](crate::data::Spec::GBFS)
+//! spec. Its auto-discovery file (`gbfs.json`) lists the URLs of the other
+//! files, which are fetched as needed.
+//!
+//! Each file states how long its contents remain valid. Files are reused until
+//! then, and fetched again once they expire, so that the methods of [`Gbfs`]
+//! can be polled to follow the state of the system. Reading a file which the
+//! feed does not list fails with [`Error::MissingFile`].
+//!
+//! Versions 1.x, 2.x and 3.x of the specification are supported, although only
+//! the most widely used fields are modelled.
+
+use std::{
+    collections::HashMap,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use bytes::Bytes;
+use chrono::{DateTime, TimeZone, Utc};
+use serde::{de::DeserializeOwned, Deserialize, Deserializer};
+
+use crate::{
+    auth::{Secret, SecretStore},
+    data::{Authorization, Feed, Spec},
+    error::decode,
+    Client, Error, Result,
+};
+
+/// Language used when a feed doesn't provide the requested one.
+const DEFAULT_LANGUAGE: &str = "en";
+
+/// A GBFS feed, as described by its auto-discovery file.
+///
+/// See also: [`Client::gbfs`]
+#[derive(Debug)]
+pub struct Gbfs {
+    client: Client,
+    authorization: Authorization,
+    secret: Option<Secret>,
+    version: String,
+    /// URLs of the files of the feed, by name.
+    urls: HashMap<String, String>,
+    files: Mutex<HashMap<&'static str, CachedFile>>,
+}
+
+#[derive(Debug)]
+struct CachedFile {
+    body: Bytes,
+    expires: Instant,
+}
+
+impl Client {
+    /// Open a GBFS feed by fetching its auto-discovery file.
+    ///
+    /// Where the feed is available in multiple languages, English is used if
+    /// possible. See [`Client::gbfs_with_language`] to choose another.
+    pub async fn gbfs(&self, feed: &Feed, secrets: &impl SecretStore) -> Result<Gbfs> {
+        self.gbfs_with_language(feed, secrets, DEFAULT_LANGUAGE)
+            .await
+    }
+
+    /// Open a GBFS feed, using the files for the given language if the feed
+    /// is available in multiple languages.
+    pub async fn gbfs_with_language(
+        &self,
+        feed: &Feed,
+        secrets: &impl SecretStore,
+        language: &str,
+    ) -> Result<Gbfs> {
+        if feed.spec != Spec::GBFS {
+            return Err(Error::InvalidArgument(format!(
+                "feed {} is not a GBFS feed",
+                feed.onestop_id
+            )));
+        }
+        let url = feed.urls.gbfs_auto_discovery.as_deref().ok_or_else(|| {
+            Error::InvalidArgument(format!(
+                "feed {} has no GBFS auto-discovery URL",
+                feed.onestop_id
+            ))
+        })?;
+
+        let body = self.fetch_feed_url(feed, url, secrets).await?;
+        let discovery: File<Discovery> = decode(&body)?;
+        let feeds = match discovery.data {
+            Discovery::Feeds(feeds) => feeds.feeds,
+            Discovery::Languages(mut languages) => {
+                let language = if languages.contains_key(language) {
+                    language.to_owned()
+                } else {
+                    // fall back to an arbitrary but consistent language
+                    let mut keys: Vec<_> = languages.keys().cloned().collect();
+                    keys.sort();
+                    keys.into_iter().next().unwrap_or_default()
+                };
+                languages
+                    .remove(&language)
+                    .map(|feeds| feeds.feeds)
+                    .unwrap_or_default()
+            }
+        };
+
+        Ok(Gbfs {
+            client: self.clone(),
+            authorization: feed.authorization.clone(),
+            secret: secrets.secret(feed),
+            version: discovery.version.unwrap_or_else(|| "1.0".to_owned()),
+            urls: feeds
+                .into_iter()
+                .map(|feed| (feed.name, feed.url))
+                .collect(),
+            files: Mutex::new(HashMap::new()),
+        })
+    }
+}
+
+impl Gbfs {
+    /// Version of the specification the feed conforms to, such as `2.3`.
+    pub fn version(&self) -> &str {
+        &self.version
+    }
+
+    /// Names of the files provided by the feed.
+    pub fn files(&self) -> impl Iterator<Item = &str> {
+        self.urls.keys().map(String::as_str)
+    }
+
+    pub async fn system_information(&self) -> Result<SystemInformation> {
+        self.file(&["system_information"]).await
+    }
+
+    pub async fn station_information(&self) -> Result<Vec<StationInformation>> {
+        let data: Stations<StationInformation> = self.file(&["station_information"]).await?;
+        Ok(data.stations)
+    }
+
+    pub async fn station_status(&self) -> Result<Vec<StationStatus>> {
+        let data: Stations<StationStatus> = self.file(&["station_status"]).await?;
+        Ok(data.stations)
+    }
+
+    /// Status of the vehicles which are not docked at a station. Since
+    /// version 3.0, this is read from `vehicle_status` and includes all
+    /// vehicles.
+    pub async fn free_bike_status(&self) -> Result<Vec<FreeBikeStatus>> {
+        let data: Bikes = self.file(&["free_bike_status", "vehicle_status"]).await?;
+        Ok(data.bikes)
+    }
+
+    pub async fn vehicle_types(&self) -> Result<Vec<VehicleType>> {
+        let data: VehicleTypes = self.file(&["vehicle_types"]).await?;
+        Ok(data.vehicle_types)
+    }
+
+    /// Read the data of the first file with one of the given names, fetching
+    /// it again if it has expired.
+    async fn file<T: DeserializeOwned>(&self, names: &[&'static str]) -> Result<T> {
+        let (name, url) = names
+            .iter()
+            .find_map(|name| Some((*name, self.urls.get(*name)?)))
+            .ok_or(Error::MissingFile(names[0]))?;
+
+        let cached = self
+            .files
+            .lock()
+            .unwrap()
+            .get(name)
+            .filter(|file| Instant::now() < file.expires)
+            .map(|file| file.body.clone());
+        if let Some(body) = cached {
+            let file: File<T> = decode(&body)?;
+            return Ok(file.data);
+        }
+
+        let body = self
+            .client
+            .fetch_external(|http| self.authorization.apply(http, url, self.secret.as_ref()))
+            .await?;
+        let file: File<T> = decode(&body)?;
+        let mut files = self.files.lock().unwrap();
+        // a TTL too long to represent is not trusted, and the file is fetched
+        // again the next time it is needed
+        match Instant::now().checked_add(Duration::from_secs(file.ttl)) {
+            Some(expires) => files.insert(name, CachedFile { body, expires }),
+            None => files.remove(name),
+        };
+        Ok(file.data)
+    }
+}
+
+/// Common structure of every GBFS file.
+#[derive(Deserialize)]
+struct File<T> {
+    /// Number of seconds before the file should be fetched again.
+    #[serde(default)]
+    ttl: u64,
+    version: Option<String>,
+    data: T,
+}
+
+/// Data of the auto-discovery file.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum Discovery {
+    /// Since version 3.0, feeds are listed directly.
+    Feeds(DiscoveryFeeds),
+    /// Before version 3.0, feeds are listed by language.
+    Languages(HashMap<String, DiscoveryFeeds>),
+}
+
+#[derive(Deserialize)]
+struct DiscoveryFeeds {
+    feeds: Vec<DiscoveryFeed>,
+}
+
+#[derive(Deserialize)]
+struct DiscoveryFeed {
+    name: String,
+    url: String,
+}
+
+#[derive(Deserialize)]
+struct Stations<T> {
+    stations: Vec<T>,
+}
+
+#[derive(Deserialize)]
+struct Bikes {
+    #[serde(alias = "vehicles")]
+    bikes: Vec<FreeBikeStatus>,
+}
+
+#[derive(Deserialize)]
+struct VehicleTypes {
+    vehicle_types: Vec<VehicleType>,
+}
+
+/// Text which may be provided in multiple languages.
+///
+/// Since version 3.0, most text is given as a list of translations.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(untagged)]
+pub enum Text {
+    Plain(String),
+    Translated(Vec<Translation>),
+}
+
+impl Text {
+    /// Text in the given language, falling back to the first translation.
+    pub fn get(&self, language: &str) -> &str {
+        match self {
+            Text::Plain(text) => text,
+            Text::Translated(translations) => translations
+                .iter()
+                .find(|translation| translation.language == language)
+                .or_else(|| translations.first())
+                .map(|translation| translation.text.as_str())
+                .unwrap_or_default(),
+        }
+    }
+}
+
+/// Text in a single language.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct Translation {
+    pub text: String,
+    /// IETF BCP 47 language code.
+    pub language: String,
+}
+
+/// Details about a bikeshare system and its operator.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct SystemInformation {
+    pub system_id: String,
+    /// Language of the feed, before version 3.0.
+    pub language: Option<String>,
+    /// Languages the feed is available in, since version 3.0.
+    pub languages: Option<Vec<String>>,
+    pub name: Text,
+    pub short_name: Option<Text>,
+    pub operator: Option<Text>,
+    pub url: Option<String>,
+    pub purchase_url: Option<String>,
+    /// Date the system began operating, as `YYYY-MM-DD`.
+    pub start_date: Option<String>,
+    pub phone_number: Option<String>,
+    pub email: Option<String>,
+    pub feed_contact_email: Option<String>,
+    /// Timezone of the system, from the IANA Time Zone Database.
+    pub timezone: String,
+    pub license_url: Option<String>,
+}
+
+/// Details of a station which vehicles can be rented from or returned to.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct StationInformation {
+    pub station_id: String,
+    pub name: Text,
+    pub short_name: Option<Text>,
+    pub lat: f64,
+    pub lon: f64,
+    pub address: Option<String>,
+    pub cross_street: Option<String>,
+    pub region_id: Option<String>,
+    pub post_code: Option<String>,
+    /// Payment methods accepted at the station, such as `key` or
+    /// `creditcard`.
+    pub rental_methods: Option<Vec<String>>,
+    /// Number of vehicles the station can hold.
+    pub capacity: Option<u64>,
+    #[serde(default, deserialize_with = "deserialize_optional_bool")]
+    pub is_virtual_station: Option<bool>,
+}
+
+/// Current availability of vehicles and docks at a station.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct StationStatus {
+    pub station_id: String,
+    #[serde(alias = "num_vehicles_available")]
+    pub num_bikes_available: u64,
+    #[serde(alias = "num_vehicles_disabled")]
+    pub num_bikes_disabled: Option<u64>,
+    pub num_docks_available: Option<u64>,
+    pub num_docks_disabled: Option<u64>,
+    #[serde(deserialize_with = "deserialize_bool")]
+    pub is_installed: bool,
+    #[serde(deserialize_with = "deserialize_bool")]
+    pub is_renting: bool,
+    #[serde(deserialize_with = "deserialize_bool")]
+    pub is_returning: bool,
+    /// When the station last reported its status.
+    #[serde(deserialize_with = "deserialize_timestamp")]
+    pub last_reported: DateTime<Utc>,
+    /// Number of available vehicles of each type.
+    pub vehicle_types_available: Option<Vec<VehicleTypeCount>>,
+}
+
+/// Number of vehicles of a single type.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct VehicleTypeCount {
+    pub vehicle_type_id: String,
+    pub count: u64,
+}
+
+/// Current state of a vehicle.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct FreeBikeStatus {
+    #[serde(alias = "vehicle_id")]
+    pub bike_id: String,
+    /// Latitude of the vehicle. Missing for vehicles docked at a station,
+    /// since version 3.0.
+    pub lat: Option<f64>,
+    pub lon: Option<f64>,
+    #[serde(deserialize_with = "deserialize_bool")]
+    pub is_reserved: bool,
+    #[serde(deserialize_with = "deserialize_bool")]
+    pub is_disabled: bool,
+    pub vehicle_type_id: Option<String>,
+    /// Station the vehicle is docked at, if any.
+    pub station_id: Option<String>,
+    /// Distance the vehicle can travel with its remaining charge or fuel.
+    pub current_range_meters: Option<f64>,
+    #[serde(default, deserialize_with = "deserialize_optional_timestamp")]
+    pub last_reported: Option<DateTime<Utc>>,
+}
+
+/// A type of vehicle available in a system.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct VehicleType {
+    pub vehicle_type_id: String,
+    /// Kind of vehicle, such as `bicycle` or `scooter`.
+    pub form_factor: String,
+    /// How the vehicle is powered, such as `human` or `electric_assist`.
+    pub propulsion_type: String,
+    /// Distance a fully charged or fueled vehicle can travel.
+    pub max_range_meters: Option<f64>,
+    pub name: Option<Text>,
+}
+
+/// Timestamps are POSIX times before version 3.0, and RFC 3339 strings since.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum Timestamp {
+    Posix(i64),
+    Rfc3339(DateTime<Utc>),
+}
+
+impl Timestamp {
+    fn into_datetime<E: serde::de::Error>(self) -> std::result::Result<DateTime<Utc>, E> {
+        match self {
+            Timestamp::Posix(secs) => Utc
+                .timestamp_opt(secs, 0)
+                .single()
+                .ok_or_else(|| E::custom(format!("invalid timestamp {}", secs))),
+            Timestamp::Rfc3339(datetime) => Ok(datetime),
+        }
+    }
+}
+
+fn deserialize_timestamp<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> std::result::Result<DateTime<Utc>, D::Error> {
+    Timestamp::deserialize(deserializer)?.into_datetime()
+}
+
+fn deserialize_optional_timestamp<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> std::result::Result<Option<DateTime<Utc>>, D::Error> {
+    Option::<Timestamp>::deserialize(deserializer)?
+        .map(Timestamp::into_datetime)
+        .transpose()
+}
+
+/// Booleans are given as `0` or `1` in version 1.x.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum Bool {
+    Bool(bool),
+    Int(u8),
+}
+
+impl From<Bool> for bool {
+    fn from(value: Bool) -> Self {
+        match value {
+            Bool::Bool(value) => value,
+            Bool::Int(value) => value != 0,
+        }
+    }
+}
+
+fn deserialize_bool<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> std::result::Result<bool, D::Error> {
+    Bool::deserialize(deserializer).map(Into::into)
+}
+
+fn deserialize_optional_bool<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> std::result::Result<Option<bool>, D::Error> {
+    Ok(Option::<Bool>::deserialize(deserializer)?.map(Into::into))
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::test_server::{feed_json, response, test_client, TestServer};
+
+    fn feed(server: &TestServer) -> Feed {
+        serde_json::from_value(feed_json(json!({
+            "onestop_id": "f-bikes~gbfs",
+            "spec": "gbfs",
+            "urls": { "gbfs_auto_discovery": format!("{}/gbfs.json", server.url()) }
+        })))
+        .unwrap()
+    }
+
+    fn json_response(body: serde_json::Value) -> Vec<u8> {
+        response(
+            200,
+            &[("Content-Type", "application/json")],
+            body.to_string(),
+        )
+    }
+
+    fn discovery_feeds(server: &TestServer, names: &[&str]) -> serde_json::Value {
+        let feeds: Vec<_> = names
+            .iter()
+            .map(|name| json!({ "name": name, "url": format!("{}/{}.json", server.url(), name) }))
+            .collect();
+        json!({ "feeds": feeds })
+    }
+
+    #[tokio::test]
+    async fn discovery_by_language() {
+        let files = TestServer::start(vec![json_response(json!({
+            "last_updated": 1_678_800_000,
+            "ttl": 60,
+            "data": {
+                "stations": [{
+                    "station_id": "1",
+                    "num_bikes_available": 3,
+                    "num_docks_available": 7,
+                    "is_installed": 1,
+                    "is_renting": 0,
+                    "is_returning": true,
+                    "last_reported": 1_678_800_000
+                }]
+            }
+        }))])
+        .await;
+        let discovery = json_response(json!({
+            "last_updated": 1_678_800_000,
+            "ttl": 0,
+            "version": "2.3",
+            "data": {
+                "en": discovery_feeds(&files, &["station_status", "system_information"]),
+                "fr": discovery_feeds(&files, &["station_status"])
+            }
+        }));
+        let server = TestServer::start(vec![discovery.clone(), discovery]).await;
+        let client = test_client(server.url());
+
+        let gbfs = client.gbfs(&feed(&server), &()).await.unwrap();
+        assert_eq!(gbfs.version(), "2.3");
+        let mut names: Vec<_> = gbfs.files().collect();
+        names.sort();
+        assert_eq!(names, ["station_status", "system_information"]);
+
+        let status = gbfs.station_status().await.unwrap();
+        assert!(status[0].is_installed);
+        assert!(!status[0].is_renting);
+        assert!(status[0].is_returning);
+        assert_eq!(
+            status[0].last_reported,
+            Utc.timestamp_opt(1_678_800_000, 0).unwrap()
+        );
+        // reused until it expires
+        assert_eq!(gbfs.station_status().await.unwrap(), status);
+        assert_eq!(files.requests().len(), 1);
+
+        let gbfs = client
+            .gbfs_with_language(&feed(&server), &(), "fr")
+            .await
+            .unwrap();
+        assert_eq!(gbfs.files().collect::<Vec<_>>(), ["station_status"]);
+        assert!(matches!(
+            gbfs.system_information().await,
+            Err(Error::MissingFile("system_information"))
+        ));
+    }
+
+    #[tokio::test]
+    async fn discovery_v3() {
+        let files = TestServer::start(vec![json_response(json!({
+            "last_updated": "2023-03-14T13:20:00Z",
+            "ttl": 60,
+            "version": "3.0",
+            "data": {
+                "vehicles": [{
+                    "vehicle_id": "a",
+                    "lat": 37.8,
+                    "lon": -122.3,
+                    "is_reserved": false,
+                    "is_disabled": true,
+                    "last_reported": "2023-03-14T06:20:00-07:00"
+                }, {
+                    "vehicle_id": "b",
+                    "station_id": "1",
+                    "is_reserved": 0,
+                    "is_disabled": 0
+                }]
+            }
+        }))])
+        .await;
+        let server = TestServer::start(vec![json_response(json!({
+            "last_updated": "2023-03-14T13:20:00Z",
+            "ttl": 0,
+            "version": "3.0",
+            "data": discovery_feeds(&files, &["vehicle_status"])
+        }))])
+        .await;
+
+        let gbfs = test_client(server.url())
+            .gbfs(&feed(&server), &())
+            .await
+            .unwrap();
+        assert_eq!(gbfs.version(), "3.0");
+        assert_eq!(gbfs.files().collect::<Vec<_>>(), ["vehicle_status"]);
+
+        let vehicles = gbfs.free_bike_status().await.unwrap();
+        assert_eq!(vehicles.len(), 2);
+        assert_eq!(vehicles[0].bike_id, "a");
+        assert!(vehicles[0].is_disabled);
+        assert_eq!(
+            vehicles[0].last_reported,
+            Some(Utc.with_ymd_and_hms(2023, 3, 14, 13, 20, 0).unwrap())
+        );
+        assert_eq!(vehicles[1].lat, None);
+        assert!(!vehicles[1].is_disabled);
+        assert_eq!(vehicles[1].last_reported, None);
+    }
+
+    #[tokio::test]
+    async fn huge_ttl() {
+        let status = json_response(json!({
+            "last_updated": 1_678_800_000,
+            "ttl": u64::MAX,
+            "data": { "stations": [] }
+        }));
+        let files = TestServer::start(vec![status.clone(), status]).await;
+        let server = TestServer::start(vec![json_response(json!({
+            "last_updated": 1_678_800_000,
+            "ttl": 0,
+            "data": { "en": discovery_feeds(&files, &["station_status"]) }
+        }))])
+        .await;
+
+        let gbfs = test_client(server.url())
+            .gbfs(&feed(&server), &())
+            .await
+            .unwrap();
+        assert!(gbfs.station_status().await.unwrap().is_empty());
+        // the file is not cached rather than overflowing its expiry time
+        assert!(gbfs.station_status().await.unwrap().is_empty());
+        assert_eq!(files.requests().len(), 2);
+    }
+
+    #[test]
+    fn booleans_and_timestamps() {
+        let status = |is_renting: serde_json::Value, last_reported: serde_json::Value| {
+            serde_json::from_value::<StationStatus>(json!({
+                "station_id": "1",
+                "num_bikes_available": 0,
+                "is_installed": true,
+                "is_renting": is_renting,
+                "is_returning": true,
+                "last_reported": last_reported
+            }))
+        };
+        let time = Utc.timestamp_opt(1_678_800_000, 0).unwrap();
+
+        for (value, expected) in [(json!(1), true), (json!(0), false), (json!(false), false)] {
+            assert_eq!(
+                status(value, json!(1_678_800_000)).unwrap().is_renting,
+                expected
+            );
+        }
+        assert!(status(json!("yes"), json!(1_678_800_000)).is_err());
+
+        for value in [json!(1_678_800_000), json!("2023-03-14T13:20:00Z")] {
+            assert_eq!(status(json!(1), value).unwrap().last_reported, time);
+        }
+        assert!(status(json!(1), json!("yesterday")).is_err());
+        assert!(status(json!(1), json!(i64::MAX)).is_err());
+    }
+}
@@ -0,0 +1,522 @@
+//! Reading static GTFS feeds without Transitland.
+//!
+//! An [`Archive`] reads the files of a GTFS feed from a zip archive, such as
+//! one fetched with [`Client::download_feed_version`](crate::Client::download_feed_version),
+//! or from a directory it was extracted to. Each file is read lazily, one row
+//! at a time, so that large files such as `stop_times.txt` need not fit in
+//! memory. Rows which cannot be parsed are reported as [`RowError`]s without
+//! interrupting the rest of the file.
+//!
+//! The record types in this module mirror the GTFS files they are read from.
+//! Calendars are combined into the same [`Calendar`](crate::data::Calendar)
+//! type returned by the Transitland API.
+
+use std::{
+    collections::{btree_map::Entry, BTreeMap},
+    fmt,
+    fs::File,
+    io::{BufReader, Read, Seek},
+    marker::PhantomData,
+    path::{Path, PathBuf},
+};
+
+use chrono::NaiveDate;
+use serde::{de::DeserializeOwned, Deserialize, Deserializer};
+use zip::ZipArchive;
+
+use crate::{
+    data::{
+        self, Accessibility, ExactTimes, LocationType, PickupDropOffType, RouteType, ServiceTime,
+        Timepoint,
+    },
+    Error, Result,
+};
+
+/// A static GTFS feed, stored as a zip archive or a directory.
+pub struct Archive {
+    source: Source,
+}
+
+trait ReadSeek: Read + Seek + Send {}
+
+impl<R: Read + Seek + Send> ReadSeek for R {}
+
+enum Source {
+    Directory(PathBuf),
+    Zip(ZipArchive<Box<dyn ReadSeek>>),
+}
+
+impl Archive {
+    /// Open a feed from a zip archive or a directory containing its files.
+    pub fn open(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        if path.is_dir() {
+            Ok(Archive {
+                source: Source::Directory(path.to_owned()),
+            })
+        } else {
+            Archive::from_zip(BufReader::new(File::open(path)?))
+        }
+    }
+
+    /// Read a feed from a zip archive, such as one held in memory.
+    pub fn from_zip(reader: impl Read + Seek + Send + 'static) -> Result<Self> {
+        let reader: Box<dyn ReadSeek> = Box::new(reader);
+        Ok(Archive {
+            source: Source::Zip(ZipArchive::new(reader)?),
+        })
+    }
+
+    /// Read the rows of the file holding records of type `R`.
+    ///
+    /// Optional files which are missing from the feed have no rows, while
+    /// missing required files produce [`Error::MissingFile`].
+    pub fn records<R: Record>(&mut self) -> Result<Records<'_, R>> {
+        let reader: Box<dyn Read + '_> = match &mut self.source {
+            Source::Directory(dir) => {
+                let path = dir.join(R::FILE);
+                if path.is_file() {
+                    Box::new(BufReader::new(File::open(path)?))
+                } else {
+                    return missing::<R>();
+                }
+            }
+            Source::Zip(zip) => {
+                // some archives keep their files in a subdirectory, so prefer
+                // the root and then the shallowest nested file, as the order of
+                // file names is unspecified
+                let nested = format!("/{}", R::FILE);
+                let name = if zip.file_names().any(|name| name == R::FILE) {
+                    Some(R::FILE.to_owned())
+                } else {
+                    zip.file_names()
+                        .filter(|name| name.ends_with(&nested))
+                        .min_by_key(|name| (name.len(), *name))
+                        .map(str::to_owned)
+                };
+                match name {
+                    Some(name) => Box::new(zip.by_name(&name)?),
+                    None => return missing::<R>(),
+                }
+            }
+        };
+
+        let mut reader = csv::ReaderBuilder::new()
+            .trim(csv::Trim::All)
+            .flexible(true)
+            .from_reader(reader);
+        let headers = reader.headers()?.clone();
+        Ok(Records {
+            reader: Some(reader),
+            headers,
+            record: csv::StringRecord::new(),
+            _marker: PhantomData,
+        })
+    }
+
+    pub fn agencies(&mut self) -> Result<Records<'_, Agency>> {
+        self.records()
+    }
+
+    pub fn stops(&mut self) -> Result<Records<'_, Stop>> {
+        self.records()
+    }
+
+    pub fn routes(&mut self) -> Result<Records<'_, Route>> {
+        self.records()
+    }
+
+    pub fn trips(&mut self) -> Result<Records<'_, Trip>> {
+        self.records()
+    }
+
+    pub fn stop_times(&mut self) -> Result<Records<'_, StopTime>> {
+        self.records()
+    }
+
+    pub fn frequencies(&mut self) -> Result<Records<'_, Frequency>> {
+        self.records()
+    }
+
+    pub fn shapes(&mut self) -> Result<Records<'_, ShapePoint>> {
+        self.records()
+    }
+
+    /// Combine `calendar.txt` and `calendar_dates.txt` into one
+    /// [`Calendar`](data::Calendar) per service, along with any rows which
+    /// could not be read.
+    ///
+    /// Services defined only in `calendar_dates.txt` are marked as
+    /// [`generated`](data::Calendar::generated), and span the dates they were
+    /// added on.
+    pub fn calendars(&mut self) -> Result<(Vec<data::Calendar>, Vec<RowError>)> {
+        let mut calendars = BTreeMap::new();
+        let mut errors = vec![];
+
+        for record in self.records::<CalendarRecord>()? {
+            match record {
+                Ok(record) => {
+                    calendars.insert(record.service_id.clone(), record.into_calendar());
+                }
+                Err(err) => errors.push(err),
+            }
+        }
+
+        let mut records = self.records::<CalendarDate>()?;
+        while let Some(record) = records.next() {
+            let record = match record {
+                Ok(record) => record,
+                Err(err) => {
+                    errors.push(err);
+                    continue;
+                }
+            };
+            let added = match record.exception_type {
+                1 => true,
+                2 => false,
+                exception_type => {
+                    // reported before the service is looked up, so that an
+                    // empty calendar is not generated for it
+                    errors.push(RowError {
+                        file: CalendarDate::FILE,
+                        line: records.line(),
+                        message: format!(
+                            "unknown exception_type {} for service {}",
+                            exception_type, record.service_id
+                        ),
+                    });
+                    continue;
+                }
+            };
+            let calendar = match calendars.entry(record.service_id.clone()) {
+                Entry::Occupied(entry) => entry.into_mut(),
+                Entry::Vacant(entry) => entry.insert(data::Calendar {
+                    service_id: Some(record.service_id),
+                    start_date: record.date,
+                    end_date: record.date,
+                    added_dates: Some(vec![]),
+                    removed_dates: Some(vec![]),
+                    generated: Some(true),
+                    monday: 0,
+                    tuesday: 0,
+                    wednesday: 0,
+                    thursday: 0,
+                    friday: 0,
+                    saturday: 0,
+                    sunday: 0,
+                }),
+            };
+            if added {
+                if calendar.generated == Some(true) {
+                    calendar.start_date = calendar.start_date.min(record.date);
+                    calendar.end_date = calendar.end_date.max(record.date);
+                }
+                calendar
+                    .added_dates
+                    .get_or_insert_with(Vec::new)
+                    .push(record.date);
+            } else {
+                calendar
+                    .removed_dates
+                    .get_or_insert_with(Vec::new)
+                    .push(record.date);
+            }
+        }
+
+        Ok((calendars.into_values().collect(), errors))
+    }
+}
+
+impl fmt::Debug for Archive {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.source {
+            Source::Directory(dir) => f.debug_tuple("Archive").field(dir).finish(),
+            Source::Zip(_) => f.debug_tuple("Archive").field(&"<zip>").finish(),
+        }
+    }
+}
+
+fn missing<'a, R: Record>() -> Result<Records<'a, R>> {
+    if R::REQUIRED {
+        Err(Error::MissingFile(R::FILE))
+    } else {
+        Ok(Records {
+            reader: None,
+            headers: csv::StringRecord::new(),
+            record: csv::StringRecord::new(),
+            _marker: PhantomData,
+        })
+    }
+}
+
+/// A type of row in a GTFS file.
+pub trait Record: DeserializeOwned {
+    /// Name of the file holding these records.
+    const FILE: &'static str;
+    /// Whether every feed must include this file.
+    const REQUIRED: bool;
+}
+
+/// Iterator over the rows of a GTFS file.
+///
+/// See also: [`Archive::records`]
+pub struct Records<'a, R> {
+    reader: Option<csv::Reader<Box<dyn Read + 'a>>>,
+    headers: csv::StringRecord,
+    record: csv::StringRecord,
+    _marker: PhantomData<R>,
+}
+
+impl<R> Records<'_, R> {
+    /// Line number of the row last returned, if any.
+    fn line(&self) -> Option<u64> {
+        self.record.position().map(|position| position.line())
+    }
+}
+
+impl<R: Record> Iterator for Records<'_, R> {
+    type Item = std::result::Result<R, RowError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let reader = self.reader.as_mut()?;
+        match reader.read_record(&mut self.record) {
+            Ok(true) => Some(
+                self.record
+                    .deserialize(Some(&self.headers))
+                    .map_err(|err| RowError::new(R::FILE, err)),
+            ),
+            Ok(false) => None,
+            Err(err) => {
+                // the underlying reader failed, so there's nothing more to read
+                self.reader = None;
+                Some(Err(RowError::new(R::FILE, err)))
+            }
+        }
+    }
+}
+
+/// A row of a GTFS file which could not be read.
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+#[error("{file}{}: {message}", line.map(|line| format!(":{}", line)).unwrap_or_default())]
+pub struct RowError {
+    /// Name of the file containing the row.
+    pub file: &'static str,
+    /// Line number of the row, if known.
+    pub line: Option<u64>,
+    /// Description of the problem.
+    pub message: String,
+}
+
+impl RowError {
+    fn new(file: &'static str, err: csv::Error) -> Self {
+        let line = err.position().map(|position| position.line());
+        let message = match err.kind() {
+            csv::ErrorKind::Deserialize { err, .. } => err.to_string(),
+            _ => err.to_string(),
+        };
+        RowError {
+            file,
+            line,
+            message,
+        }
+    }
+}
+
+macro_rules! impl_record {
+    ($type:ty, $file:expr, $required:expr) => {
+        impl Record for $type {
+            const FILE: &'static str = $file;
+            const REQUIRED: bool = $required;
+        }
+    };
+}
+
+/// A row of `agency.txt`.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct Agency {
+    pub agency_id: Option<String>,
+    pub agency_name: String,
+    pub agency_url: String,
+    pub agency_timezone: String,
+    pub agency_lang: Option<String>,
+    pub agency_phone: Option<String>,
+    pub agency_fare_url: Option<String>,
+    pub agency_email: Option<String>,
+}
+
+impl_record!(Agency, "agency.txt", true);
+
+/// A row of `stops.txt`.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct Stop {
+    pub stop_id: String,
+    pub stop_code: Option<String>,
+    pub stop_name: Option<String>,
+    pub stop_desc: Option<String>,
+    pub stop_lat: Option<f64>,
+    pub stop_lon: Option<f64>,
+    pub zone_id: Option<String>,
+    pub stop_url: Option<String>,
+    pub location_type: Option<LocationType>,
+    pub parent_station: Option<String>,
+    pub stop_timezone: Option<String>,
+    pub wheelchair_boarding: Option<Accessibility>,
+    pub level_id: Option<String>,
+    pub platform_code: Option<String>,
+}
+
+impl_record!(Stop, "stops.txt", true);
+
+/// A row of `routes.txt`.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct Route {
+    pub route_id: String,
+    pub agency_id: Option<String>,
+    pub route_short_name: Option<String>,
+    pub route_long_name: Option<String>,
+    pub route_desc: Option<String>,
+    pub route_type: RouteType,
+    pub route_url: Option<String>,
+    pub route_color: Option<String>,
+    pub route_text_color: Option<String>,
+    pub route_sort_order: Option<u64>,
+}
+
+impl_record!(Route, "routes.txt", true);
+
+/// A row of `trips.txt`.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct Trip {
+    pub route_id: String,
+    pub service_id: String,
+    pub trip_id: String,
+    pub trip_headsign: Option<String>,
+    pub trip_short_name: Option<String>,
+    pub direction_id: Option<u64>,
+    pub block_id: Option<String>,
+    pub shape_id: Option<String>,
+    pub wheelchair_accessible: Option<Accessibility>,
+    pub bikes_allowed: Option<Accessibility>,
+}
+
+impl_record!(Trip, "trips.txt", true);
+
+/// A row of `stop_times.txt`.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct StopTime {
+    pub trip_id: String,
+    /// Missing if the time should be interpolated.
+    pub arrival_time: Option<ServiceTime>,
+    /// Missing if the time should be interpolated.
+    pub departure_time: Option<ServiceTime>,
+    pub stop_id: String,
+    pub stop_sequence: u64,
+    pub stop_headsign: Option<String>,
+    pub pickup_type: Option<PickupDropOffType>,
+    pub drop_off_type: Option<PickupDropOffType>,
+    pub shape_dist_traveled: Option<f64>,
+    pub timepoint: Option<Timepoint>,
+}
+
+impl_record!(StopTime, "stop_times.txt", true);
+
+/// A row of `frequencies.txt`.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct Frequency {
+    pub trip_id: String,
+    /// When the trip begins repeating.
+    pub start_time: ServiceTime,
+    /// When the trip stops repeating.
+    pub end_time: ServiceTime,
+    pub headway_secs: u64,
+    pub exact_times: Option<ExactTimes>,
+}
+
+impl_record!(Frequency, "frequencies.txt", false);
+
+impl From<Frequency> for data::Frequency {
+    fn from(frequency: Frequency) -> Self {
+        data::Frequency {
+            start_time: frequency.start_time,
+            end_time: frequency.end_time,
+            headway_secs: frequency.headway_secs,
+            exact_times: frequency
+                .exact_times
+                .unwrap_or(data::ExactTimes::FrequencyBased),
+        }
+    }
+}
+
+/// A row of `shapes.txt`, being one point of a shape.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct ShapePoint {
+    pub shape_id: String,
+    pub shape_pt_lat: f64,
+    pub shape_pt_lon: f64,
+    pub shape_pt_sequence: u64,
+    pub shape_dist_traveled: Option<f64>,
+}
+
+impl_record!(ShapePoint, "shapes.txt", false);
+
+/// A row of `calendar.txt`.
+///
+/// See also: [`Archive::calendars`]
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct CalendarRecord {
+    pub service_id: String,
+    pub monday: u64,
+    pub tuesday: u64,
+    pub wednesday: u64,
+    pub thursday: u64,
+    pub friday: u64,
+    pub saturday: u64,
+    pub sunday: u64,
+    #[serde(deserialize_with = "deserialize_date")]
+    pub start_date: NaiveDate,
+    #[serde(deserialize_with = "deserialize_date")]
+    pub end_date: NaiveDate,
+}
+
+impl_record!(CalendarRecord, "calendar.txt", false);
+
+impl CalendarRecord {
+    fn into_calendar(self) -> data::Calendar {
+        data::Calendar {
+            service_id: Some(self.service_id),
+            start_date: self.start_date,
+            end_date: self.end_date,
+            added_dates: Some(vec![]),
+            removed_dates: Some(vec![]),
+            generated: Some(false),
+            monday: self.monday,
+            tuesday: self.tuesday,
+            wednesday: self.wednesday,
+            thursday: self.thursday,
+            friday: self.friday,
+            saturday: self.saturday,
+            sunday: self.sunday,
+        }
+    }
+}
+
+/// A row of `calendar_dates.txt`.
+///
+/// See also: [`Archive::calendars`]
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct CalendarDate {
+    pub service_id: String,
+    #[serde(deserialize_with = "deserialize_date")]
+    pub date: NaiveDate,
+    pub exception_type: u64,
+}
+
+impl_record!(CalendarDate, "calendar_dates.txt", false);
+
+fn deserialize_date<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> std::result::Result<NaiveDate, D::Error> {
+    let date = String::deserialize(deserializer)?;
+    NaiveDate::parse_from_str(&date, "%Y%m%d")
+        .map_err(|_| serde::de::Error::custom(format!("invalid GTFS date `{}`", date)))
+}
@@ -1,4 +1,34 @@
 mod api;
 pub use api::*;
 
+mod client;
+pub use client::*;
+
+mod departures;
+pub use departures::*;
+
+mod download;
+pub use download::*;
+
+mod error;
+pub use error::*;
+
+mod rate_limit;
+pub use rate_limit::{Quota, RateLimitStats};
+
+mod retry;
+pub use retry::*;
+
+#[cfg(test)]
+mod test_server;
+
+pub mod auth;
+pub mod cache;
 pub mod data;
+pub mod dmfr;
+pub mod gbfs;
+pub mod gtfs;
+pub mod license;
+pub mod query;
+#[cfg(feature = "realtime")]
+pub mod realtime;
@@ -0,0 +1,179 @@
+//! Checking feed licenses against an intended use.
+//!
+//! A [`LicensePolicy`] describes how feeds will be used, such as in a
+//! commercial product or redistributed to others. Checking a feed against it
+//! finds which of those uses its [`License`] permits, and the attribution it
+//! requires.
+//!
+//! Transitland records permissions as reviewed by its contributors, so many
+//! are [`Unknown`](Permission::Unknown). Feeds with unknown permissions are
+//! reported separately from those which forbid a use, so that they can be
+//! reviewed by hand.
+
+use crate::data::{Feed, License, Permission};
+
+/// A use of a feed which its license may or may not permit.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum LicenseUse {
+    /// Use for commercial purposes.
+    CommercialUse,
+    /// Redistribution of the feed in its entirety.
+    Redistribution,
+    /// Creation and sharing of products derived from the feed.
+    DerivedProduct,
+    /// Use without displaying attribution.
+    UseWithoutAttribution,
+    /// Keeping modifications of the feed private, rather than sharing them
+    /// under the same license.
+    PrivateModifications,
+}
+
+impl LicenseUse {
+    /// Whether `license` permits this use.
+    pub fn permission(&self, license: &License) -> Permission {
+        match self {
+            LicenseUse::CommercialUse => license.commercial_use_allowed,
+            LicenseUse::Redistribution => license.redistribution_allowed,
+            LicenseUse::DerivedProduct => license.create_derived_product,
+            LicenseUse::UseWithoutAttribution => license.use_without_attribution,
+            LicenseUse::PrivateModifications => license.share_alike_optional,
+        }
+    }
+}
+
+/// Overall result of a license check.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum LicenseStatus {
+    /// Every intended use is permitted.
+    Allowed,
+    /// At least one intended use is forbidden.
+    Forbidden,
+    /// No intended use is forbidden, but some are not known to be permitted.
+    Unknown,
+}
+
+/// The uses a feed will be put to.
+///
+/// By default, no use beyond displaying the feed with attribution is
+/// intended, which every license is assumed to permit.
+///
+/// See also: [`LicensePolicy::check`]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct LicensePolicy {
+    uses: Vec<LicenseUse>,
+}
+
+impl LicensePolicy {
+    pub fn new() -> Self {
+        LicensePolicy::default()
+    }
+
+    /// Intend to use feeds for commercial purposes.
+    pub fn with_commercial_use(self, commercial_use: bool) -> Self {
+        self.with_use(LicenseUse::CommercialUse, commercial_use)
+    }
+
+    /// Intend to redistribute feeds in their entirety.
+    pub fn with_redistribution(self, redistribution: bool) -> Self {
+        self.with_use(LicenseUse::Redistribution, redistribution)
+    }
+
+    /// Intend to create and share products derived from feeds.
+    pub fn with_derived_product(self, derived_product: bool) -> Self {
+        self.with_use(LicenseUse::DerivedProduct, derived_product)
+    }
+
+    /// Intend to use feeds without displaying their attribution.
+    pub fn without_attribution(self, without_attribution: bool) -> Self {
+        self.with_use(LicenseUse::UseWithoutAttribution, without_attribution)
+    }
+
+    /// Intend to keep modifications of feeds private.
+    pub fn with_private_modifications(self, private_modifications: bool) -> Self {
+        self.with_use(LicenseUse::PrivateModifications, private_modifications)
+    }
+
+    fn with_use(mut self, license_use: LicenseUse, intended: bool) -> Self {
+        self.uses.retain(|existing| *existing != license_use);
+        if intended {
+            self.uses.push(license_use);
+        }
+        self
+    }
+
+    /// Check a feed's license against the intended uses.
+    ///
+    /// Feeds without their own attribution text are attributed by name.
+    pub fn check(&self, feed: &Feed) -> LicenseCheck {
+        let mut check = self.check_license(&feed.license);
+        if check.attribution_required && check.attribution_text.is_none() {
+            let name = feed.name.as_deref().unwrap_or(&feed.onestop_id);
+            check.attribution_text = Some(match &feed.license.spdx_identifier {
+                Some(spdx_identifier) => format!("{} ({})", name, spdx_identifier),
+                None => name.to_owned(),
+            });
+        }
+        check
+    }
+
+    /// Check a license against the intended uses.
+    pub fn check_license(&self, license: &License) -> LicenseCheck {
+        let mut forbidden = vec![];
+        let mut unknown = vec![];
+        for license_use in &self.uses {
+            match license_use.permission(license) {
+                Permission::Yes => {}
+                Permission::No => forbidden.push(*license_use),
+                Permission::Unknown => unknown.push(*license_use),
+            }
+        }
+
+        let status = if !forbidden.is_empty() {
+            LicenseStatus::Forbidden
+        } else if !unknown.is_empty() {
+            LicenseStatus::Unknown
+        } else {
+            LicenseStatus::Allowed
+        };
+        // a policy intending to omit attribution is forbidden from doing so,
+        // rather than exempt from it
+        let attribution_required = license.use_without_attribution != Permission::Yes;
+
+        LicenseCheck {
+            status,
+            forbidden,
+            unknown,
+            attribution_required,
+            attribution_text: license
+                .attribution_text
+                .clone()
+                .filter(|_| attribution_required),
+            attribution_instructions: license
+                .attribution_instructions
+                .clone()
+                .filter(|_| attribution_required),
+        }
+    }
+}
+
+/// Result of checking a license against a [`LicensePolicy`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LicenseCheck {
+    pub status: LicenseStatus,
+    /// Intended uses which the license forbids.
+    pub forbidden: Vec<LicenseUse>,
+    /// Intended uses which the license is not known to permit.
+    pub unknown: Vec<LicenseUse>,
+    /// Whether the feed must be attributed wherever it is used.
+    pub attribution_required: bool,
+    /// Text to display as attribution, if required.
+    pub attribution_text: Option<String>,
+    /// How the attribution must be displayed, if specified.
+    pub attribution_instructions: Option<String>,
+}
+
+impl LicenseCheck {
+    pub fn is_allowed(&self) -> bool {
+        self.status == LicenseStatus::Allowed
+    }
+}
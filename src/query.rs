@@ -0,0 +1,321 @@
+//! Filters for narrowing down searches.
+//!
+//! Each [`TransitlandObject`](crate::TransitlandObject) has its own set of
+//! filters, given by its associated
+//! [`Query`](crate::TransitlandObject::Query) type:
+//! - [`Feed`](crate::data::Feed): [`FeedQuery`]
+//! - [`FeedVersion`](crate::data::FeedVersion): [`FeedVersionQuery`]
+//! - [`Agency`](crate::data::Agency): [`AgencyQuery`]
+//! - [`Operator`](crate::data::Operator): [`OperatorQuery`]
+//! - [`Route`](crate::data::Route): [`RouteQuery`]
+//! - [`Stop`](crate::data::Stop): [`StopQuery`]
+//! - [`Trip`](crate::data::Trip): [`TripQuery`]
+
+use chrono::NaiveDate;
+
+use crate::{
+    data::{LocationType, RouteType, Spec},
+    Error, Result,
+};
+
+/// Filters for searches of one type of object.
+pub trait QueryParams: Clone + Default {
+    /// Query parameters corresponding to the filters which are set.
+    fn params(&self) -> Vec<(&'static str, String)>;
+}
+
+/// Appends `(name, value)` to `params` for every filter field which is set.
+macro_rules! push_params {
+    ($params:ident, $self:ident, $($field:ident),* $(,)?) => {
+        $(
+            if let Some(value) = &$self.$field {
+                $params.push((stringify!($field), value.to_string()));
+            }
+        )*
+    };
+}
+
+/// Filters for searches of [`Feed`](crate::data::Feed)s.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct FeedQuery {
+    /// Type of data contained in the feed.
+    pub spec: Option<Spec>,
+    /// Only feeds whose last fetch did (`true`) or did not (`false`) produce
+    /// an error.
+    pub fetch_error: Option<bool>,
+    /// Only feeds with this tag.
+    pub tag_key: Option<String>,
+    /// Only feeds whose [`FeedQuery::tag_key`] tag has this value.
+    pub tag_value: Option<String>,
+}
+
+impl QueryParams for FeedQuery {
+    fn params(&self) -> Vec<(&'static str, String)> {
+        let mut params = vec![];
+        if let Some(spec) = self.spec {
+            params.push(("spec", spec.as_str().to_owned()));
+        }
+        push_params!(params, self, fetch_error, tag_key, tag_value);
+        params
+    }
+}
+
+/// Filters for searches of [`FeedVersion`](crate::data::FeedVersion)s.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FeedVersionQuery {
+    /// Only versions of the feed with this OnestopID.
+    pub feed_onestop_id: Option<String>,
+    /// Only the version with this SHA1 hash.
+    pub sha1: Option<String>,
+}
+
+impl QueryParams for FeedVersionQuery {
+    fn params(&self) -> Vec<(&'static str, String)> {
+        let mut params = vec![];
+        push_params!(params, self, feed_onestop_id, sha1);
+        params
+    }
+}
+
+/// Filters for searches of [`Agency`](crate::data::Agency)s.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct AgencyQuery {
+    /// Only agencies with this GTFS `agency_id`.
+    pub agency_id: Option<String>,
+    /// Only agencies with this GTFS `agency_name`.
+    pub agency_name: Option<String>,
+    /// Only agencies from the feed with this OnestopID.
+    pub feed_onestop_id: Option<String>,
+}
+
+impl QueryParams for AgencyQuery {
+    fn params(&self) -> Vec<(&'static str, String)> {
+        let mut params = vec![];
+        push_params!(params, self, agency_id, agency_name, feed_onestop_id);
+        params
+    }
+}
+
+/// Filters for searches of [`Operator`](crate::data::Operator)s.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct OperatorQuery {
+    /// Only operators with this tag.
+    pub tag_key: Option<String>,
+    /// Only operators whose [`OperatorQuery::tag_key`] tag has this value.
+    pub tag_value: Option<String>,
+    /// Only operators with an agency in this country.
+    pub adm0_name: Option<String>,
+    /// Only operators with an agency in this state or province.
+    pub adm1_name: Option<String>,
+    /// Only operators with an agency in this city.
+    pub city_name: Option<String>,
+}
+
+impl QueryParams for OperatorQuery {
+    fn params(&self) -> Vec<(&'static str, String)> {
+        let mut params = vec![];
+        push_params!(params, self, tag_key, tag_value, adm0_name, adm1_name, city_name);
+        params
+    }
+}
+
+/// Filters for searches of [`Route`](crate::data::Route)s.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RouteQuery {
+    /// Only routes with this GTFS `route_type`.
+    pub route_type: Option<RouteType>,
+    /// Only routes of the operator with this OnestopID.
+    pub operator_onestop_id: Option<String>,
+    /// Only routes of the agency with this integer ID.
+    pub agency_id: Option<u64>,
+}
+
+impl QueryParams for RouteQuery {
+    fn params(&self) -> Vec<(&'static str, String)> {
+        let mut params = vec![];
+        push_params!(params, self, route_type, operator_onestop_id, agency_id);
+        params
+    }
+}
+
+/// Filters for searches of [`Stop`](crate::data::Stop)s.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct StopQuery {
+    /// Only stops served by any of the routes or operators with these
+    /// OnestopIDs.
+    pub served_by_onestop_ids: Vec<String>,
+    /// Only stops with this GTFS `location_type`.
+    pub location_type: Option<LocationType>,
+}
+
+impl QueryParams for StopQuery {
+    fn params(&self) -> Vec<(&'static str, String)> {
+        let mut params = vec![];
+        if !self.served_by_onestop_ids.is_empty() {
+            params.push((
+                "served_by_onestop_ids",
+                self.served_by_onestop_ids.join(","),
+            ));
+        }
+        push_params!(params, self, location_type);
+        params
+    }
+}
+
+/// Filters for searches of [`Trip`](crate::data::Trip)s.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TripQuery {
+    /// Only trips with this GTFS `trip_id`.
+    pub trip_id: Option<String>,
+    /// Only trips running on this date.
+    pub service_date: Option<NaiveDate>,
+}
+
+impl QueryParams for TripQuery {
+    fn params(&self) -> Vec<(&'static str, String)> {
+        let mut params = vec![];
+        push_params!(params, self, trip_id, service_date);
+        params
+    }
+}
+
+/// A geographic filter for searches.
+///
+/// Applies to searches for [`Agency`](crate::data::Agency),
+/// [`Operator`](crate::data::Operator), [`Route`](crate::data::Route) and
+/// [`Stop`](crate::data::Stop). Filters can only be created through
+/// [`GeoFilter::radius`] and [`GeoFilter::bbox`], which check that their
+/// coordinates are valid.
+///
+/// See also: [`Request::with_geo`](crate::Request::with_geo)
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum GeoFilter {
+    /// Objects within a distance of a point.
+    Radius(Radius),
+    /// Objects intersecting a bounding box.
+    BoundingBox(BoundingBox),
+}
+
+/// A circle to search within, as created by [`GeoFilter::radius`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Radius {
+    lat: f64,
+    lon: f64,
+    radius: f64,
+}
+
+impl Radius {
+    /// Latitude of the center.
+    pub fn lat(&self) -> f64 {
+        self.lat
+    }
+
+    /// Longitude of the center.
+    pub fn lon(&self) -> f64 {
+        self.lon
+    }
+
+    /// Distance from the center, in meters.
+    pub fn radius(&self) -> f64 {
+        self.radius
+    }
+}
+
+/// A bounding box to search within, as created by [`GeoFilter::bbox`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BoundingBox {
+    min_lon: f64,
+    min_lat: f64,
+    max_lon: f64,
+    max_lat: f64,
+}
+
+impl BoundingBox {
+    pub fn min_lon(&self) -> f64 {
+        self.min_lon
+    }
+
+    pub fn min_lat(&self) -> f64 {
+        self.min_lat
+    }
+
+    pub fn max_lon(&self) -> f64 {
+        self.max_lon
+    }
+
+    pub fn max_lat(&self) -> f64 {
+        self.max_lat
+    }
+}
+
+impl GeoFilter {
+    /// Filter to objects within `radius` meters of the given point.
+    pub fn radius(lat: f64, lon: f64, radius: f64) -> Result<Self> {
+        check_lat(lat)?;
+        check_lon(lon)?;
+        if !radius.is_finite() || radius <= 0.0 {
+            return Err(Error::InvalidArgument(format!(
+                "radius must be a positive number of meters, got {}",
+                radius
+            )));
+        }
+        Ok(GeoFilter::Radius(Radius { lat, lon, radius }))
+    }
+
+    /// Filter to objects intersecting the given bounding box.
+    pub fn bbox(min_lon: f64, min_lat: f64, max_lon: f64, max_lat: f64) -> Result<Self> {
+        check_lon(min_lon)?;
+        check_lat(min_lat)?;
+        check_lon(max_lon)?;
+        check_lat(max_lat)?;
+        if min_lon > max_lon || min_lat > max_lat {
+            return Err(Error::InvalidArgument(format!(
+                "bounding box minimum ({}, {}) must not exceed its maximum ({}, {})",
+                min_lon, min_lat, max_lon, max_lat
+            )));
+        }
+        Ok(GeoFilter::BoundingBox(BoundingBox {
+            min_lon,
+            min_lat,
+            max_lon,
+            max_lat,
+        }))
+    }
+
+    pub(crate) fn params(&self) -> Vec<(&'static str, String)> {
+        match self {
+            GeoFilter::Radius(radius) => vec![
+                ("lat", radius.lat.to_string()),
+                ("lon", radius.lon.to_string()),
+                ("radius", radius.radius.to_string()),
+            ],
+            GeoFilter::BoundingBox(bbox) => vec![(
+                "bbox",
+                format!(
+                    "{},{},{},{}",
+                    bbox.min_lon, bbox.min_lat, bbox.max_lon, bbox.max_lat
+                ),
+            )],
+        }
+    }
+}
+
+fn check_lat(lat: f64) -> Result<()> {
+    if !(-90.0..=90.0).contains(&lat) {
+        return Err(Error::InvalidArgument(format!(
+            "latitude must be between -90 and 90, got {}",
+            lat
+        )));
+    }
+    Ok(())
+}
+
+fn check_lon(lon: f64) -> Result<()> {
+    if !(-180.0..=180.0).contains(&lon) {
+        return Err(Error::InvalidArgument(format!(
+            "longitude must be between -180 and 180, got {}",
+            lon
+        )));
+    }
+    Ok(())
+}
@@ -0,0 +1,279 @@
+use std::{sync::Mutex, time::Duration};
+
+use tokio::time::Instant;
+
+/// A limit on the number of requests which may be made within a period.
+///
+/// See also: [`ClientBuilder::with_quota`](crate::ClientBuilder::with_quota)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Quota {
+    requests: u32,
+    period: Duration,
+}
+
+impl Quota {
+    /// Allow `requests` requests in every `period`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `requests` is zero or `period` is empty.
+    pub fn new(requests: u32, period: Duration) -> Self {
+        assert!(requests > 0, "quota must allow at least one request");
+        assert!(!period.is_zero(), "quota period must not be empty");
+        Quota { requests, period }
+    }
+
+    pub fn per_second(requests: u32) -> Self {
+        Quota::new(requests, Duration::from_secs(1))
+    }
+
+    pub fn per_minute(requests: u32) -> Self {
+        Quota::new(requests, Duration::from_secs(60))
+    }
+
+    pub fn per_hour(requests: u32) -> Self {
+        Quota::new(requests, Duration::from_secs(60 * 60))
+    }
+
+    /// Allow `requests` requests every 30 days.
+    pub fn per_month(requests: u32) -> Self {
+        Quota::new(requests, Duration::from_secs(30 * 24 * 60 * 60))
+    }
+}
+
+/// Statistics on requests delayed to stay within a client's quotas.
+///
+/// See also: [`Client::rate_limit_stats`](crate::Client::rate_limit_stats)
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RateLimitStats {
+    /// Number of requests which passed through the rate limiter.
+    pub requests: u64,
+    /// Number of requests which had to wait before being sent.
+    pub delayed_requests: u64,
+    /// Total time spent waiting by all requests.
+    pub total_wait: Duration,
+    /// Longest time spent waiting by a single request.
+    pub max_wait: Duration,
+}
+
+/// Token-bucket rate limiter enforcing a set of quotas.
+///
+/// Each quota is a bucket holding up to [`Quota::requests`] tokens, refilled
+/// continuously over its period. Callers reserve a token from every bucket up
+/// front, letting buckets go into debt, and then wait until that debt would
+/// have been repaid; this queues concurrent callers in the order they arrived.
+#[derive(Debug)]
+pub(crate) struct RateLimiter {
+    buckets: Mutex<Vec<Bucket>>,
+    stats: Mutex<RateLimitStats>,
+}
+
+#[derive(Debug)]
+struct Bucket {
+    capacity: f64,
+    /// Tokens added per second.
+    rate: f64,
+    tokens: f64,
+    updated: Instant,
+}
+
+impl RateLimiter {
+    pub(crate) fn new(quotas: &[Quota]) -> Self {
+        let now = Instant::now();
+        let buckets = quotas
+            .iter()
+            .map(|quota| Bucket {
+                capacity: quota.requests as f64,
+                rate: quota.requests as f64 / quota.period.as_secs_f64(),
+                tokens: quota.requests as f64,
+                updated: now,
+            })
+            .collect();
+        RateLimiter {
+            buckets: Mutex::new(buckets),
+            stats: Mutex::new(RateLimitStats::default()),
+        }
+    }
+
+    /// Wait until a request may be sent without exceeding any quota.
+    ///
+    /// If the returned future is dropped before it completes, such as by a
+    /// timeout, its token is given back.
+    pub(crate) async fn acquire(&self) {
+        let wait = self.reserve();
+        if !wait.is_zero() {
+            let reservation = Reservation {
+                limiter: self,
+                used: false,
+            };
+            tokio::time::sleep(wait).await;
+            reservation.use_token();
+        }
+
+        let mut stats = self.stats.lock().unwrap();
+        stats.requests += 1;
+        if !wait.is_zero() {
+            stats.delayed_requests += 1;
+            stats.total_wait += wait;
+            stats.max_wait = stats.max_wait.max(wait);
+        }
+    }
+
+    pub(crate) fn stats(&self) -> RateLimitStats {
+        self.stats.lock().unwrap().clone()
+    }
+
+    /// Take a token from every bucket, returning how long the caller must
+    /// wait before using it.
+    fn reserve(&self) -> Duration {
+        let now = Instant::now();
+        let mut buckets = self.buckets.lock().unwrap();
+
+        let mut wait = Duration::ZERO;
+        for bucket in buckets.iter_mut() {
+            let elapsed = now.duration_since(bucket.updated).as_secs_f64();
+            bucket.tokens = (bucket.tokens + elapsed * bucket.rate).min(bucket.capacity);
+            bucket.updated = now;
+
+            bucket.tokens -= 1.0;
+            if bucket.tokens < 0.0 {
+                wait = wait.max(Duration::from_secs_f64(-bucket.tokens / bucket.rate));
+            }
+        }
+        wait
+    }
+
+    /// Return a token taken by [`reserve`](Self::reserve) which was not used.
+    fn refund(&self) {
+        let mut buckets = self.buckets.lock().unwrap();
+        for bucket in buckets.iter_mut() {
+            bucket.tokens = (bucket.tokens + 1.0).min(bucket.capacity);
+        }
+    }
+}
+
+/// A token reserved by a caller which is waiting to use it, refunded if the
+/// caller stops waiting.
+struct Reservation<'a> {
+    limiter: &'a RateLimiter,
+    used: bool,
+}
+
+impl Reservation<'_> {
+    fn use_token(mut self) {
+        self.used = true;
+    }
+}
+
+impl Drop for Reservation<'_> {
+    fn drop(&mut self) {
+        if !self.used {
+            self.limiter.refund();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Round to the nearest millisecond, ignoring floating point error.
+    fn millis(duration: Duration) -> u64 {
+        (duration.as_secs_f64() * 1000.0).round() as u64
+    }
+
+    fn reserve(limiter: &RateLimiter, count: usize) -> Vec<u64> {
+        (0..count).map(|_| millis(limiter.reserve())).collect()
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn burst_then_queue() {
+        let limiter = RateLimiter::new(&[Quota::per_second(2)]);
+        // the bucket starts full, then later callers queue behind earlier ones
+        assert_eq!(reserve(&limiter, 5), [0, 0, 500, 1000, 1500]);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn refill_up_to_capacity() {
+        let limiter = RateLimiter::new(&[Quota::per_second(2)]);
+        assert_eq!(reserve(&limiter, 3), [0, 0, 500]);
+
+        // the debt is repaid after half a second, then the bucket refills
+        tokio::time::advance(Duration::from_millis(1000)).await;
+        assert_eq!(reserve(&limiter, 2), [0, 500]);
+
+        // a long idle period only refills the bucket to its capacity
+        tokio::time::advance(Duration::from_secs(60)).await;
+        assert_eq!(reserve(&limiter, 3), [0, 0, 500]);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn strictest_quota_wins() {
+        let limiter = RateLimiter::new(&[Quota::per_second(10), Quota::per_minute(3)]);
+        assert_eq!(reserve(&limiter, 5), [0, 0, 0, 20_000, 40_000]);
+
+        let limiter = RateLimiter::new(&[Quota::per_minute(60), Quota::per_second(1)]);
+        assert_eq!(reserve(&limiter, 3), [0, 1000, 2000]);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn unlimited() {
+        let limiter = RateLimiter::new(&[]);
+        assert_eq!(reserve(&limiter, 3), [0, 0, 0]);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn acquire_waits_and_records_stats() {
+        let limiter = RateLimiter::new(&[Quota::per_second(2)]);
+        let start = Instant::now();
+        for _ in 0..4 {
+            limiter.acquire().await;
+        }
+        // the third request waits 500ms, after which the fourth waits 500ms
+        assert_eq!(millis(start.elapsed()), 1000);
+        let stats = limiter.stats();
+        assert_eq!((stats.requests, stats.delayed_requests), (4, 2));
+        assert_eq!(millis(stats.total_wait), 1000);
+        assert_eq!(millis(stats.max_wait), 500);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn concurrent_callers_queue_in_order() {
+        let limiter = std::sync::Arc::new(RateLimiter::new(&[Quota::per_second(1)]));
+        let start = Instant::now();
+        let tasks: Vec<_> = (0..3)
+            .map(|_| {
+                let limiter = limiter.clone();
+                tokio::spawn(async move {
+                    limiter.acquire().await;
+                    millis(start.elapsed())
+                })
+            })
+            .collect();
+        let mut elapsed = vec![];
+        for task in tasks {
+            elapsed.push(task.await.unwrap());
+        }
+        elapsed.sort();
+        assert_eq!(elapsed, [0, 1000, 2000]);
+        assert_eq!(millis(limiter.stats().total_wait), 3000);
+        assert_eq!(millis(limiter.stats().max_wait), 2000);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn cancelled_acquire_is_refunded() {
+        let limiter = RateLimiter::new(&[Quota::per_second(1)]);
+        limiter.acquire().await;
+
+        // give up on waiting for the second token
+        let timeout = Duration::from_millis(100);
+        assert!(tokio::time::timeout(timeout, limiter.acquire())
+            .await
+            .is_err());
+
+        // so the next caller only waits for the first token to be repaid
+        assert_eq!(reserve(&limiter, 1), [900]);
+        assert_eq!(limiter.stats().requests, 1);
+        assert_eq!(limiter.stats().delayed_requests, 0);
+    }
+}
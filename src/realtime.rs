@@ -0,0 +1,528 @@
+//! Reading GTFS Realtime feeds.
+//!
+//! Feeds list the URLs of their realtime data in [`Urls`](crate::data::Urls).
+//! These are fetched with [`Client::vehicle_positions`],
+//! [`Client::trip_updates`] and [`Client::alerts`], or with
+//! [`Client::fetch_realtime`] for any other URL, and decoded from the
+//! [GTFS Realtime](https://gtfs.org/realtime/reference/) protobuf format into
+//! a [`FeedMessage`].
+//!
+//! Realtime URLs are usually served by the agency rather than Transitland, and
+//! many require a secret such as an API key issued by the agency. Secrets are
+//! looked up from a [`SecretStore`] and sent as described by the feed's
+//! [`Authorization`](crate::data::Authorization).
+//!
+//! Extensions to the format are ignored. This module requires the `realtime`
+//! feature.
+
+use crate::{auth::SecretStore, data::Feed, Client, Error, Result};
+
+impl Client {
+    /// Fetch and decode the GTFS Realtime feed at `url`.
+    ///
+    /// The request is sent without any authorization; the URLs of feeds
+    /// requiring a secret can be fetched with [`Client::fetch_feed_url`].
+    pub async fn fetch_realtime(&self, url: &str) -> Result<FeedMessage> {
+        let body = self.fetch_external(|http| Ok(http.get(url))).await?;
+        Ok(prost::Message::decode(body)?)
+    }
+
+    /// Fetch the current positions of a feed's vehicles.
+    pub async fn vehicle_positions(
+        &self,
+        feed: &Feed,
+        secrets: &impl SecretStore,
+    ) -> Result<Vec<VehiclePosition>> {
+        let url = realtime_url(
+            feed,
+            &feed.urls.realtime_vehicle_positions,
+            "vehicle positions",
+        )?;
+        let message = self.fetch_feed_realtime(feed, url, secrets).await?;
+        Ok(message.vehicle_positions().cloned().collect())
+    }
+
+    /// Fetch the current updates to a feed's trips.
+    pub async fn trip_updates(
+        &self,
+        feed: &Feed,
+        secrets: &impl SecretStore,
+    ) -> Result<Vec<TripUpdate>> {
+        let url = realtime_url(feed, &feed.urls.realtime_trip_updates, "trip updates")?;
+        let message = self.fetch_feed_realtime(feed, url, secrets).await?;
+        Ok(message.trip_updates().cloned().collect())
+    }
+
+    /// Fetch the current alerts of a feed.
+    pub async fn alerts(&self, feed: &Feed, secrets: &impl SecretStore) -> Result<Vec<Alert>> {
+        let url = realtime_url(feed, &feed.urls.realtime_alerts, "alerts")?;
+        let message = self.fetch_feed_realtime(feed, url, secrets).await?;
+        Ok(message.alerts().cloned().collect())
+    }
+
+    async fn fetch_feed_realtime(
+        &self,
+        feed: &Feed,
+        url: &str,
+        secrets: &impl SecretStore,
+    ) -> Result<FeedMessage> {
+        let body = self.fetch_feed_url(feed, url, secrets).await?;
+        Ok(prost::Message::decode(body)?)
+    }
+}
+
+fn realtime_url<'a>(feed: &Feed, url: &'a Option<String>, kind: &str) -> Result<&'a str> {
+    url.as_deref().ok_or_else(|| {
+        Error::InvalidArgument(format!(
+            "feed {} has no realtime {} URL",
+            feed.onestop_id, kind
+        ))
+    })
+}
+
+/// The contents of a GTFS Realtime feed.
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct FeedMessage {
+    #[prost(message, required, tag = "1")]
+    pub header: FeedHeader,
+    #[prost(message, repeated, tag = "2")]
+    pub entity: Vec<FeedEntity>,
+}
+
+impl FeedMessage {
+    pub fn vehicle_positions(&self) -> impl Iterator<Item = &VehiclePosition> {
+        self.entity
+            .iter()
+            .filter_map(|entity| entity.vehicle.as_ref())
+    }
+
+    pub fn trip_updates(&self) -> impl Iterator<Item = &TripUpdate> {
+        self.entity
+            .iter()
+            .filter_map(|entity| entity.trip_update.as_ref())
+    }
+
+    pub fn alerts(&self) -> impl Iterator<Item = &Alert> {
+        self.entity
+            .iter()
+            .filter_map(|entity| entity.alert.as_ref())
+    }
+}
+
+/// Metadata about a feed message.
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct FeedHeader {
+    /// Version of the GTFS Realtime specification, such as `2.0`.
+    #[prost(string, required, tag = "1")]
+    pub gtfs_realtime_version: String,
+    #[prost(enumeration = "Incrementality", optional, tag = "2")]
+    pub incrementality: Option<i32>,
+    /// When the content of the message was created, in seconds since the Unix
+    /// epoch.
+    #[prost(uint64, optional, tag = "3")]
+    pub timestamp: Option<u64>,
+}
+
+/// Whether a feed message contains the whole dataset, or only changes to it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, prost::Enumeration)]
+#[repr(i32)]
+pub enum Incrementality {
+    FullDataset = 0,
+    Differential = 1,
+}
+
+/// An update to a single entity of a feed.
+///
+/// Exactly one of `trip_update`, `vehicle` and `alert` is expected to be set,
+/// unless the entity is being deleted.
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct FeedEntity {
+    /// Unique identifier of the entity within the feed.
+    #[prost(string, required, tag = "1")]
+    pub id: String,
+    /// Whether the entity should be deleted, in differential feeds.
+    #[prost(bool, optional, tag = "2")]
+    pub is_deleted: Option<bool>,
+    #[prost(message, optional, tag = "3")]
+    pub trip_update: Option<TripUpdate>,
+    #[prost(message, optional, tag = "4")]
+    pub vehicle: Option<VehiclePosition>,
+    #[prost(message, optional, tag = "5")]
+    pub alert: Option<Alert>,
+}
+
+/// Realtime progress of a trip, as predicted arrival and departure times at
+/// its stops.
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct TripUpdate {
+    #[prost(message, required, tag = "1")]
+    pub trip: TripDescriptor,
+    #[prost(message, optional, tag = "3")]
+    pub vehicle: Option<VehicleDescriptor>,
+    /// Updates to the stops of the trip, ordered by stop sequence.
+    #[prost(message, repeated, tag = "2")]
+    pub stop_time_update: Vec<StopTimeUpdate>,
+    /// When the prediction was made, in seconds since the Unix epoch.
+    #[prost(uint64, optional, tag = "4")]
+    pub timestamp: Option<u64>,
+    /// Current delay of the trip in seconds, for stops without an update.
+    #[prost(int32, optional, tag = "5")]
+    pub delay: Option<i32>,
+}
+
+/// Predicted timing of an arrival or departure.
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct StopTimeEvent {
+    /// Delay relative to the schedule, in seconds.
+    #[prost(int32, optional, tag = "1")]
+    pub delay: Option<i32>,
+    /// Absolute time of the event, in seconds since the Unix epoch.
+    #[prost(int64, optional, tag = "2")]
+    pub time: Option<i64>,
+    /// Expected error of the prediction, in seconds. Zero if certain.
+    #[prost(int32, optional, tag = "3")]
+    pub uncertainty: Option<i32>,
+}
+
+/// Realtime update to the arrival and departure of a trip at one stop.
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct StopTimeUpdate {
+    #[prost(uint32, optional, tag = "1")]
+    pub stop_sequence: Option<u32>,
+    #[prost(string, optional, tag = "4")]
+    pub stop_id: Option<String>,
+    #[prost(message, optional, tag = "2")]
+    pub arrival: Option<StopTimeEvent>,
+    #[prost(message, optional, tag = "3")]
+    pub departure: Option<StopTimeEvent>,
+    #[prost(
+        enumeration = "StopTimeScheduleRelationship",
+        optional,
+        tag = "5",
+        default = "Scheduled"
+    )]
+    pub schedule_relationship: Option<i32>,
+}
+
+/// Relationship between a stop time update and the static schedule.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, prost::Enumeration)]
+#[repr(i32)]
+pub enum StopTimeScheduleRelationship {
+    Scheduled = 0,
+    Skipped = 1,
+    NoData = 2,
+    Unscheduled = 3,
+}
+
+/// Realtime position of a vehicle.
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct VehiclePosition {
+    /// Trip the vehicle is serving.
+    #[prost(message, optional, tag = "1")]
+    pub trip: Option<TripDescriptor>,
+    #[prost(message, optional, tag = "8")]
+    pub vehicle: Option<VehicleDescriptor>,
+    #[prost(message, optional, tag = "2")]
+    pub position: Option<Position>,
+    /// Stop sequence of the current stop of the vehicle.
+    #[prost(uint32, optional, tag = "3")]
+    pub current_stop_sequence: Option<u32>,
+    /// ID of the current stop of the vehicle.
+    #[prost(string, optional, tag = "7")]
+    pub stop_id: Option<String>,
+    /// Status of the vehicle relative to its current stop.
+    #[prost(
+        enumeration = "VehicleStopStatus",
+        optional,
+        tag = "4",
+        default = "InTransitTo"
+    )]
+    pub current_status: Option<i32>,
+    /// When the position was measured, in seconds since the Unix epoch.
+    #[prost(uint64, optional, tag = "5")]
+    pub timestamp: Option<u64>,
+    #[prost(enumeration = "CongestionLevel", optional, tag = "6")]
+    pub congestion_level: Option<i32>,
+    #[prost(enumeration = "OccupancyStatus", optional, tag = "9")]
+    pub occupancy_status: Option<i32>,
+    /// Percentage of the vehicle's normal capacity which is occupied.
+    #[prost(uint32, optional, tag = "10")]
+    pub occupancy_percentage: Option<u32>,
+}
+
+/// Status of a vehicle relative to its current stop.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, prost::Enumeration)]
+#[repr(i32)]
+pub enum VehicleStopStatus {
+    IncomingAt = 0,
+    StoppedAt = 1,
+    InTransitTo = 2,
+}
+
+/// Congestion affecting a vehicle.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, prost::Enumeration)]
+#[repr(i32)]
+pub enum CongestionLevel {
+    UnknownCongestionLevel = 0,
+    RunningSmoothly = 1,
+    StopAndGo = 2,
+    Congestion = 3,
+    SevereCongestion = 4,
+}
+
+/// How full a vehicle is.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, prost::Enumeration)]
+#[repr(i32)]
+pub enum OccupancyStatus {
+    Empty = 0,
+    ManySeatsAvailable = 1,
+    FewSeatsAvailable = 2,
+    StandingRoomOnly = 3,
+    CrushedStandingRoomOnly = 4,
+    Full = 5,
+    NotAcceptingPassengers = 6,
+    NoDataAvailable = 7,
+    NotBoardable = 8,
+}
+
+/// Geographic position of a vehicle.
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct Position {
+    #[prost(float, required, tag = "1")]
+    pub latitude: f32,
+    #[prost(float, required, tag = "2")]
+    pub longitude: f32,
+    /// Direction of the vehicle in degrees clockwise from true north.
+    #[prost(float, optional, tag = "3")]
+    pub bearing: Option<f32>,
+    /// Distance travelled by the vehicle, in meters.
+    #[prost(double, optional, tag = "4")]
+    pub odometer: Option<f64>,
+    /// Speed of the vehicle, in meters per second.
+    #[prost(float, optional, tag = "5")]
+    pub speed: Option<f32>,
+}
+
+/// A realtime notice of a disruption.
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct Alert {
+    /// Times during which the alert should be shown. Always shown if empty.
+    #[prost(message, repeated, tag = "1")]
+    pub active_period: Vec<TimeRange>,
+    /// Entities affected by the alert.
+    #[prost(message, repeated, tag = "5")]
+    pub informed_entity: Vec<EntitySelector>,
+    #[prost(enumeration = "Cause", optional, tag = "6", default = "UnknownCause")]
+    pub cause: Option<i32>,
+    #[prost(enumeration = "Effect", optional, tag = "7", default = "UnknownEffect")]
+    pub effect: Option<i32>,
+    /// URL for more information about the alert.
+    #[prost(message, optional, tag = "8")]
+    pub url: Option<TranslatedString>,
+    #[prost(message, optional, tag = "10")]
+    pub header_text: Option<TranslatedString>,
+    #[prost(message, optional, tag = "11")]
+    pub description_text: Option<TranslatedString>,
+}
+
+/// Cause of an alert.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, prost::Enumeration)]
+#[repr(i32)]
+pub enum Cause {
+    UnknownCause = 1,
+    OtherCause = 2,
+    TechnicalProblem = 3,
+    Strike = 4,
+    Demonstration = 5,
+    Accident = 6,
+    Holiday = 7,
+    Weather = 8,
+    Maintenance = 9,
+    Construction = 10,
+    PoliceActivity = 11,
+    MedicalEmergency = 12,
+}
+
+/// Effect of an alert on service.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, prost::Enumeration)]
+#[repr(i32)]
+pub enum Effect {
+    NoService = 1,
+    ReducedService = 2,
+    SignificantDelays = 3,
+    Detour = 4,
+    AdditionalService = 5,
+    ModifiedService = 6,
+    OtherEffect = 7,
+    UnknownEffect = 8,
+    StopMoved = 9,
+    NoEffect = 10,
+    AccessibilityIssue = 11,
+}
+
+/// A period of time, in seconds since the Unix epoch. Open-ended if either
+/// bound is missing.
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct TimeRange {
+    #[prost(uint64, optional, tag = "1")]
+    pub start: Option<u64>,
+    #[prost(uint64, optional, tag = "2")]
+    pub end: Option<u64>,
+}
+
+/// Selects the entities of the static feed affected by an alert.
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct EntitySelector {
+    #[prost(string, optional, tag = "1")]
+    pub agency_id: Option<String>,
+    #[prost(string, optional, tag = "2")]
+    pub route_id: Option<String>,
+    #[prost(int32, optional, tag = "3")]
+    pub route_type: Option<i32>,
+    #[prost(message, optional, tag = "4")]
+    pub trip: Option<TripDescriptor>,
+    #[prost(string, optional, tag = "5")]
+    pub stop_id: Option<String>,
+    #[prost(uint32, optional, tag = "6")]
+    pub direction_id: Option<u32>,
+}
+
+/// Identifies an instance of a trip of the static feed.
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct TripDescriptor {
+    #[prost(string, optional, tag = "1")]
+    pub trip_id: Option<String>,
+    #[prost(string, optional, tag = "5")]
+    pub route_id: Option<String>,
+    #[prost(uint32, optional, tag = "6")]
+    pub direction_id: Option<u32>,
+    /// Scheduled start time of the trip instance, as `HH:MM:SS`.
+    #[prost(string, optional, tag = "2")]
+    pub start_time: Option<String>,
+    /// Service date of the trip instance, as `YYYYMMDD`.
+    #[prost(string, optional, tag = "3")]
+    pub start_date: Option<String>,
+    #[prost(enumeration = "TripScheduleRelationship", optional, tag = "4")]
+    pub schedule_relationship: Option<i32>,
+}
+
+/// Relationship between a trip and the static schedule.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, prost::Enumeration)]
+#[repr(i32)]
+pub enum TripScheduleRelationship {
+    Scheduled = 0,
+    Added = 1,
+    Unscheduled = 2,
+    Canceled = 3,
+    Replacement = 5,
+    Duplicated = 6,
+    Deleted = 7,
+}
+
+/// Identifies a vehicle.
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct VehicleDescriptor {
+    /// Internal identifier of the vehicle.
+    #[prost(string, optional, tag = "1")]
+    pub id: Option<String>,
+    /// Label shown to riders, such as a train number.
+    #[prost(string, optional, tag = "2")]
+    pub label: Option<String>,
+    #[prost(string, optional, tag = "3")]
+    pub license_plate: Option<String>,
+}
+
+/// Text in one or more languages.
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct TranslatedString {
+    #[prost(message, repeated, tag = "1")]
+    pub translation: Vec<Translation>,
+}
+
+impl TranslatedString {
+    /// Text in the given language, falling back to the text without a
+    /// language, and then to the first translation.
+    pub fn get(&self, language: Option<&str>) -> Option<&str> {
+        let find = |language: Option<&str>| {
+            self.translation
+                .iter()
+                .find(|translation| translation.language.as_deref() == language)
+        };
+        language
+            .and_then(|language| find(Some(language)))
+            .or_else(|| find(None))
+            .or_else(|| self.translation.first())
+            .map(|translation| translation.text.as_str())
+    }
+}
+
+/// Text in a single language.
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct Translation {
+    #[prost(string, required, tag = "1")]
+    pub text: String,
+    /// BCP-47 language code, if not the feed's default language.
+    #[prost(string, optional, tag = "2")]
+    pub language: Option<String>,
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use serde_json::json;
+
+    use super::*;
+    use crate::{
+        auth::Secret,
+        test_server::{feed_json, response, test_client, TestServer},
+    };
+
+    // shared with the decoding tests in tests/realtime.rs
+    include!("../tests/fixtures/realtime_feed.rs");
+
+    #[tokio::test]
+    async fn authorized_fetch() {
+        let protobuf = response(200, &[("Content-Type", "application/x-protobuf")], FEED);
+        let server = TestServer::start(vec![protobuf.clone(), protobuf]).await;
+        let feed: Feed = serde_json::from_value(feed_json(json!({
+            "spec": "gtfs-rt",
+            "urls": {
+                "realtime_vehicle_positions": format!("{}/vehicles.pb", server.url()),
+                "realtime_trip_updates": format!("{}/trips.pb", server.url())
+            },
+            "authorization": { "type": "query_param", "param_name": "token" }
+        })))
+        .unwrap();
+        let secrets = HashMap::from([("f-9q9-bart".to_owned(), Secret::Key("s3cret".into()))]);
+        let client = test_client(server.url());
+
+        let vehicles = client.vehicle_positions(&feed, &secrets).await.unwrap();
+        assert_eq!(vehicles.len(), 1);
+        assert_eq!(vehicles[0].stop_id.as_deref(), Some("MCAR"));
+        let updates = client.trip_updates(&feed, &secrets).await.unwrap();
+        assert_eq!(updates.len(), 1);
+        assert_eq!(updates[0].delay, Some(90));
+
+        let requests = server.requests();
+        assert!(requests[0].starts_with("get /vehicles.pb?token=s3cret "));
+        assert!(requests[1].starts_with("get /trips.pb?token=s3cret "));
+        // realtime URLs are not sent the Transitland API key
+        assert!(requests.iter().all(|request| !request.contains("apikey")));
+    }
+
+    #[tokio::test]
+    async fn missing_secret() {
+        let feed: Feed = serde_json::from_value(feed_json(json!({
+            "urls": { "realtime_vehicle_positions": "http://127.0.0.1:1/vehicles.pb" },
+            "authorization": { "type": "header", "param_name": "X-Api-Key" }
+        })))
+        .unwrap();
+        assert!(matches!(
+            test_client("http://127.0.0.1:1")
+                .vehicle_positions(&feed, &())
+                .await,
+            Err(Error::InvalidArgument(_))
+        ));
+    }
+}
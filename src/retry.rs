@@ -0,0 +1,223 @@
+use std::time::Duration;
+
+use rand::Rng;
+use reqwest::StatusCode;
+
+use crate::Error;
+
+/// Policy for retrying failed requests.
+///
+/// Requests are retried when they time out, fail to connect, are rate limited
+/// (HTTP 429) or hit a transient server error (HTTP 500, 502, 503 or 504).
+/// Between attempts, the client waits for the duration given by the server's
+/// `Retry-After` header if present, and otherwise backs off exponentially from
+/// [`RetryPolicy::with_base_delay`]. Either way, the delay is limited by
+/// [`RetryPolicy::with_max_delay`].
+///
+/// All requests made by this crate are idempotent GETs, so they are retried by
+/// default. Use [`RetryPolicy::none`] to disable retries.
+#[derive(Debug, Clone)]
+pub struct RetryPolicy {
+    max_attempts: u32,
+    base_delay: Duration,
+    max_delay: Duration,
+    jitter: bool,
+}
+
+impl RetryPolicy {
+    pub fn new() -> Self {
+        RetryPolicy {
+            max_attempts: 3,
+            base_delay: Duration::from_millis(500),
+            max_delay: Duration::from_secs(30),
+            jitter: true,
+        }
+    }
+
+    /// A policy which never retries.
+    pub fn none() -> Self {
+        RetryPolicy::new().with_max_attempts(1)
+    }
+
+    /// Give up after this many attempts in total, including the first.
+    pub fn with_max_attempts(mut self, max_attempts: u32) -> Self {
+        self.max_attempts = max_attempts.max(1);
+        self
+    }
+
+    /// Wait this long before the first retry, doubling for each retry after.
+    pub fn with_base_delay(mut self, base_delay: Duration) -> Self {
+        self.base_delay = base_delay;
+        self
+    }
+
+    /// Never wait for longer than this between attempts.
+    ///
+    /// This also limits delays requested by the server with `Retry-After`, so
+    /// that a misbehaving server cannot stall a request indefinitely.
+    pub fn with_max_delay(mut self, max_delay: Duration) -> Self {
+        self.max_delay = max_delay;
+        self
+    }
+
+    /// Randomize backoff delays to between half and all of their nominal
+    /// length, so that concurrent clients do not retry in lockstep.
+    pub fn with_jitter(mut self, jitter: bool) -> Self {
+        self.jitter = jitter;
+        self
+    }
+
+    /// How long to wait before retrying after the given (1-indexed) attempt
+    /// failed with `error`, or `None` if the request should not be retried.
+    pub(crate) fn delay(&self, attempt: u32, error: &Error) -> Option<Duration> {
+        if attempt >= self.max_attempts || !is_transient(error) {
+            return None;
+        }
+        if let Error::RateLimited {
+            retry_after: Some(retry_after),
+            ..
+        }
+        | Error::Server {
+            retry_after: Some(retry_after),
+            ..
+        } = error
+        {
+            return Some((*retry_after).min(self.max_delay));
+        }
+
+        let backoff = self
+            .base_delay
+            .saturating_mul(2u32.saturating_pow(attempt - 1))
+            .min(self.max_delay);
+        if self.jitter {
+            Some(backoff.mul_f64(rand::thread_rng().gen_range(0.5..=1.0)))
+        } else {
+            Some(backoff)
+        }
+    }
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        RetryPolicy::new()
+    }
+}
+
+fn is_transient(error: &Error) -> bool {
+    match error {
+        Error::RateLimited { .. } => true,
+        Error::Server { error, .. } => matches!(
+            error.status,
+            StatusCode::INTERNAL_SERVER_ERROR
+                | StatusCode::BAD_GATEWAY
+                | StatusCode::SERVICE_UNAVAILABLE
+                | StatusCode::GATEWAY_TIMEOUT
+        ),
+        Error::Transport(error) => error.is_timeout() || error.is_connect(),
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ApiError;
+
+    fn api_error(status: StatusCode) -> ApiError {
+        ApiError {
+            status,
+            message: None,
+        }
+    }
+
+    fn server_error(status: StatusCode, retry_after: Option<Duration>) -> Error {
+        Error::Server {
+            retry_after,
+            error: api_error(status),
+        }
+    }
+
+    fn policy() -> RetryPolicy {
+        RetryPolicy::new()
+            .with_max_attempts(5)
+            .with_base_delay(Duration::from_secs(1))
+            .with_max_delay(Duration::from_secs(10))
+            .with_jitter(false)
+    }
+
+    #[test]
+    fn exponential_backoff() {
+        let error = server_error(StatusCode::BAD_GATEWAY, None);
+        let delays: Vec<_> = (1..=5)
+            .map(|attempt| policy().delay(attempt, &error))
+            .collect();
+        assert_eq!(
+            delays,
+            [
+                Some(Duration::from_secs(1)),
+                Some(Duration::from_secs(2)),
+                Some(Duration::from_secs(4)),
+                Some(Duration::from_secs(8)),
+                None,
+            ]
+        );
+
+        let capped = policy().with_max_attempts(10).delay(6, &error);
+        assert_eq!(capped, Some(Duration::from_secs(10)));
+    }
+
+    #[test]
+    fn jitter() {
+        let error = server_error(StatusCode::SERVICE_UNAVAILABLE, None);
+        let policy = policy().with_jitter(true);
+        for _ in 0..100 {
+            let delay = policy.delay(3, &error).unwrap();
+            assert!(delay >= Duration::from_secs(2) && delay <= Duration::from_secs(4));
+        }
+    }
+
+    #[test]
+    fn retry_after() {
+        let rate_limited = Error::RateLimited {
+            retry_after: Some(Duration::from_secs(3)),
+            error: api_error(StatusCode::TOO_MANY_REQUESTS),
+        };
+        assert_eq!(
+            policy().delay(1, &rate_limited),
+            Some(Duration::from_secs(3))
+        );
+
+        let unavailable = server_error(
+            StatusCode::SERVICE_UNAVAILABLE,
+            Some(Duration::from_secs(7)),
+        );
+        assert_eq!(
+            policy().delay(1, &unavailable),
+            Some(Duration::from_secs(7))
+        );
+
+        // delays requested by the server are limited by the maximum delay
+        let unavailable = server_error(
+            StatusCode::SERVICE_UNAVAILABLE,
+            Some(Duration::from_secs(60 * 60)),
+        );
+        assert_eq!(
+            policy().delay(1, &unavailable),
+            Some(Duration::from_secs(10))
+        );
+    }
+
+    #[test]
+    fn permanent_errors() {
+        let policy = policy();
+        let not_found = Error::NotFound(api_error(StatusCode::NOT_FOUND));
+        assert_eq!(policy.delay(1, &not_found), None);
+        let not_implemented = server_error(StatusCode::NOT_IMPLEMENTED, None);
+        assert_eq!(policy.delay(1, &not_implemented), None);
+        let rate_limited = Error::RateLimited {
+            retry_after: None,
+            error: api_error(StatusCode::TOO_MANY_REQUESTS),
+        };
+        assert_eq!(RetryPolicy::none().delay(1, &rate_limited), None);
+    }
+}
@@ -0,0 +1,120 @@
+//! A minimal HTTP server for testing requests made by the client.
+
+use std::sync::{Arc, Mutex};
+
+use serde_json::{json, Value};
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::TcpListener,
+};
+
+use crate::{Client, ClientBuilder, RetryPolicy};
+
+/// A server answering each connection with the next of a list of canned
+/// responses, and recording the requests it received.
+pub(crate) struct TestServer {
+    url: String,
+    requests: Arc<Mutex<Vec<String>>>,
+}
+
+impl TestServer {
+    pub(crate) async fn start(responses: Vec<Vec<u8>>) -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let requests = Arc::new(Mutex::new(vec![]));
+
+        let received = requests.clone();
+        tokio::spawn(async move {
+            for response in responses {
+                let (mut stream, _) = listener.accept().await.unwrap();
+                let mut request = vec![];
+                let mut buf = [0; 1024];
+                while !request.ends_with(b"\r\n\r\n") {
+                    let len = stream.read(&mut buf).await.unwrap();
+                    if len == 0 {
+                        break;
+                    }
+                    request.extend_from_slice(&buf[..len]);
+                }
+                received
+                    .lock()
+                    .unwrap()
+                    .push(String::from_utf8_lossy(&request).into_owned());
+                stream.write_all(&response).await.unwrap();
+                stream.shutdown().await.unwrap();
+            }
+        });
+
+        TestServer { url, requests }
+    }
+
+    /// Base URL of the server, without a trailing slash.
+    pub(crate) fn url(&self) -> &str {
+        &self.url
+    }
+
+    /// Heads of the requests received so far, lowercased for matching.
+    pub(crate) fn requests(&self) -> Vec<String> {
+        let requests = self.requests.lock().unwrap();
+        requests
+            .iter()
+            .map(|request| request.to_lowercase())
+            .collect()
+    }
+}
+
+/// An HTTP response with the given status, headers and body, closing the
+/// connection afterwards.
+pub(crate) fn response(status: u16, headers: &[(&str, &str)], body: impl AsRef<[u8]>) -> Vec<u8> {
+    let body = body.as_ref();
+    let mut head = format!(
+        "HTTP/1.1 {} Test\r\nContent-Length: {}\r\nConnection: close\r\n",
+        status,
+        body.len()
+    );
+    for (name, value) in headers {
+        head.push_str(&format!("{}: {}\r\n", name, value));
+    }
+    head.push_str("\r\n");
+    [head.as_bytes(), body].concat()
+}
+
+/// A client builder sending requests to `base_url`, which gives up on the
+/// first failure rather than retrying.
+pub(crate) fn test_client_builder(base_url: &str) -> ClientBuilder {
+    Client::builder("secret")
+        .with_base_url(base_url)
+        .with_retry_policy(RetryPolicy::none())
+}
+
+/// A client sending requests to `base_url`, which gives up on the first
+/// failure rather than retrying.
+pub(crate) fn test_client(base_url: &str) -> Client {
+    test_client_builder(base_url).build().unwrap()
+}
+
+/// A [`Feed`](crate::data::Feed) as returned by the API, with the given
+/// top-level fields replacing their defaults.
+pub(crate) fn feed_json(fields: Value) -> Value {
+    let mut feed = json!({
+        "id": 1,
+        "onestop_id": "f-9q9-bart",
+        "name": "BART",
+        "spec": "gtfs",
+        "feed_namespace_id": null,
+        "associated_feeds": null,
+        "languages": null,
+        "urls": {},
+        "license": {},
+        "authorization": {},
+        "geometry": null,
+        "feed_state": { "feed_version": null },
+        "feed_versions": []
+    });
+    if let Value::Object(fields) = fields {
+        for (name, value) in fields {
+            feed[name] = value;
+        }
+    }
+    feed
+}
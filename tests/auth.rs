@@ -0,0 +1,155 @@
+use reqwest::{
+    header::{HeaderValue, AUTHORIZATION},
+    Request,
+};
+use transitworld::{
+    auth::Secret,
+    data::{Authorization, AuthorizationType},
+    Error,
+};
+
+const URL: &str = "https://example.com/gtfs.zip";
+
+fn authorization(auth_type: Option<AuthorizationType>, param_name: Option<&str>) -> Authorization {
+    Authorization {
+        auth_type,
+        param_name: param_name.map(Into::into),
+        ..Default::default()
+    }
+}
+
+fn apply(
+    authorization: &Authorization,
+    url: &str,
+    secret: Option<Secret>,
+) -> transitworld::Result<Request> {
+    let request = authorization.apply(&reqwest::Client::new(), url, secret.as_ref())?;
+    Ok(request.build().unwrap())
+}
+
+fn basic() -> Secret {
+    Secret::Basic {
+        username: "user".into(),
+        password: "pass".into(),
+    }
+}
+
+#[test]
+fn none() {
+    for auth_type in [None, Some(AuthorizationType::None)] {
+        let request = apply(&authorization(auth_type, None), URL, Some("key".into())).unwrap();
+        assert_eq!(request.url().as_str(), URL);
+        assert!(request.headers().is_empty());
+
+        let request = apply(&authorization(auth_type, None), URL, None).unwrap();
+        assert_eq!(request.url().as_str(), URL);
+    }
+}
+
+#[test]
+fn header() {
+    let authorization = authorization(Some(AuthorizationType::Header), Some("x-api-key"));
+    let request = apply(&authorization, URL, Some("key".into())).unwrap();
+    assert_eq!(request.url().as_str(), URL);
+    let value = &request.headers()["x-api-key"];
+    assert_eq!(value, "key");
+    assert!(value.is_sensitive());
+}
+
+#[test]
+fn basic_auth() {
+    let authorization = authorization(Some(AuthorizationType::BasicAuth), None);
+    let request = apply(&authorization, URL, Some(basic())).unwrap();
+    assert_eq!(request.url().as_str(), URL);
+    assert_eq!(
+        request.headers()[AUTHORIZATION],
+        HeaderValue::from_static("Basic dXNlcjpwYXNz")
+    );
+
+    assert!(matches!(
+        apply(&authorization, URL, Some("key".into())),
+        Err(Error::InvalidArgument(_))
+    ));
+}
+
+#[test]
+fn query_param() {
+    let authorization = authorization(Some(AuthorizationType::QueryParam), Some("api_key"));
+    let request = apply(
+        &authorization,
+        "https://example.com/gtfs?a=1",
+        Some("k y".into()),
+    )
+    .unwrap();
+    assert_eq!(
+        request.url().as_str(),
+        "https://example.com/gtfs?a=1&api_key=k+y"
+    );
+    assert!(request.headers().is_empty());
+}
+
+#[test]
+fn path_segment() {
+    let authorization = authorization(Some(AuthorizationType::PathSegment), None);
+    let request = apply(
+        &authorization,
+        "https://example.com/{}/gtfs.zip",
+        Some("key".into()),
+    )
+    .unwrap();
+    assert_eq!(request.url().as_str(), "https://example.com/key/gtfs.zip");
+
+    // the secret can't escape its segment
+    let request = apply(
+        &authorization,
+        "https://example.com/{}/gtfs.zip",
+        Some("k/y?a=1#b %".into()),
+    )
+    .unwrap();
+    assert_eq!(
+        request.url().as_str(),
+        "https://example.com/k%2Fy%3Fa=1%23b%20%25/gtfs.zip"
+    );
+    assert_eq!(request.url().query(), None);
+
+    assert!(matches!(
+        apply(&authorization, URL, Some("key".into())),
+        Err(Error::InvalidArgument(_))
+    ));
+}
+
+#[test]
+fn invalid_secrets() {
+    for auth_type in [
+        AuthorizationType::Header,
+        AuthorizationType::BasicAuth,
+        AuthorizationType::QueryParam,
+        AuthorizationType::PathSegment,
+    ] {
+        let authorization = authorization(Some(auth_type), Some("key"));
+        assert!(matches!(
+            apply(&authorization, URL, None),
+            Err(Error::InvalidArgument(_))
+        ));
+    }
+
+    for auth_type in [AuthorizationType::Header, AuthorizationType::QueryParam] {
+        assert!(matches!(
+            apply(
+                &authorization(Some(auth_type), Some("key")),
+                URL,
+                Some(basic())
+            ),
+            Err(Error::InvalidArgument(_))
+        ));
+        // the parameter name is required
+        assert!(matches!(
+            apply(
+                &authorization(Some(auth_type), None),
+                URL,
+                Some("key".into())
+            ),
+            Err(Error::InvalidArgument(_))
+        ));
+    }
+}
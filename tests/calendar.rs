@@ -0,0 +1,74 @@
+use chrono::NaiveDate;
+use transitworld::data::Calendar;
+
+fn date(day: u32) -> NaiveDate {
+    NaiveDate::from_ymd_opt(2023, 7, day).unwrap()
+}
+
+/// Weekdays in the first two weeks of July 2023, except for July 4th.
+fn weekdays() -> Calendar {
+    Calendar {
+        service_id: Some("weekday".into()),
+        start_date: date(1),
+        end_date: date(14),
+        added_dates: None,
+        removed_dates: Some(vec![date(4)]),
+        generated: Some(false),
+        monday: 1,
+        tuesday: 1,
+        wednesday: 1,
+        thursday: 1,
+        friday: 1,
+        saturday: 0,
+        sunday: 0,
+    }
+}
+
+/// Sundays in the first two weeks of July 2023, plus July 4th.
+fn holidays() -> Calendar {
+    Calendar {
+        service_id: Some("sunday".into()),
+        added_dates: Some(vec![date(4)]),
+        removed_dates: None,
+        monday: 0,
+        tuesday: 0,
+        wednesday: 0,
+        thursday: 0,
+        friday: 0,
+        sunday: 1,
+        ..weekdays()
+    }
+}
+
+#[test]
+fn is_active_on() {
+    let weekdays = weekdays();
+    assert!(weekdays.is_active_on(date(3)));
+    assert!(!weekdays.is_active_on(date(4)));
+    assert!(!weekdays.is_active_on(date(8)));
+    assert!(!weekdays.is_active_on(date(17)));
+    assert!(holidays().is_active_on(date(4)));
+}
+
+#[test]
+fn service_dates() {
+    let dates: Vec<_> = weekdays().service_dates().collect();
+    assert_eq!(dates, [3, 5, 6, 7, 10, 11, 12, 13, 14].map(date).to_vec());
+
+    let mut calendar = holidays();
+    calendar.added_dates = Some(vec![date(30)]);
+    let dates: Vec<_> = calendar.service_dates().collect();
+    assert_eq!(dates, [2, 9, 30].map(date).to_vec());
+}
+
+#[test]
+fn set_operations() {
+    let union = weekdays().union(&holidays());
+    assert_eq!(union.service_dates().count(), 12);
+    assert!(union.is_active_on(date(4)));
+    assert_eq!((union.start_date, union.end_date), (date(2), date(14)));
+
+    let intersection = weekdays().intersection(&holidays());
+    assert_eq!(intersection.service_dates().next(), None);
+    assert!(weekdays().intersection(&weekdays()).is_active_on(date(3)));
+}
@@ -0,0 +1,176 @@
+use transitworld::{
+    data::{AuthorizationType, Permission, Spec},
+    dmfr::{Registry, ValidationError},
+};
+
+fn fixture(name: &str) -> Registry {
+    Registry::read(format!(
+        "{}/tests/fixtures/{}",
+        env!("CARGO_MANIFEST_DIR"),
+        name
+    ))
+    .unwrap()
+}
+
+fn error(path: &str, message: &str) -> ValidationError {
+    ValidationError {
+        path: path.into(),
+        message: message.into(),
+    }
+}
+
+#[test]
+fn read() {
+    let registry = fixture("bart.dmfr.json");
+    assert_eq!(registry.feeds.len(), 2);
+    assert_eq!(registry.operators.len(), 1);
+
+    let feed = &registry.feeds[1];
+    assert_eq!(feed.spec, Spec::GTFSRealtime);
+    assert_eq!(feed.associated_feeds, ["f-9q9-bart"]);
+    assert_eq!(
+        feed.authorization.auth_type,
+        Some(AuthorizationType::PathSegment)
+    );
+    assert_eq!(
+        registry.feeds[0].license.use_without_attribution,
+        Permission::No
+    );
+
+    let operators: Vec<_> = registry
+        .all_operators()
+        .map(|(feed, operator)| (feed.map(|feed| feed.id.as_str()), &*operator.onestop_id))
+        .collect();
+    assert_eq!(
+        operators,
+        [
+            (Some("f-9q9-bart"), "o-9q9-bart"),
+            (None, "o-9q9-bayarea~511")
+        ]
+    );
+}
+
+#[test]
+fn round_trip() {
+    let registry = fixture("bart.dmfr.json");
+    assert_eq!(
+        Registry::from_slice(registry.to_json().as_bytes()).unwrap(),
+        registry
+    );
+
+    // a schema is added if missing
+    let without_schema = Registry {
+        schema: None,
+        ..registry.clone()
+    };
+    assert_eq!(
+        Registry::from_slice(without_schema.to_json().as_bytes()).unwrap(),
+        registry
+    );
+}
+
+#[test]
+fn validate() {
+    assert_eq!(fixture("bart.dmfr.json").validate(), Ok(()));
+
+    assert_eq!(
+        fixture("invalid.dmfr.json").validate(),
+        Err(vec![
+            error("feeds[1]", "duplicate of feeds[0] `f-9q9-bart`"),
+            error(
+                "feeds[2].urls.realtime_alerts",
+                "must contain `{}` for path_segment authorization"
+            ),
+            error(
+                "operators[0].associated_feeds[0]",
+                "unknown feed `f-9q9-actransit`"
+            ),
+            error(
+                "operators[0].associated_feeds[1]",
+                "feed_onestop_id is required outside of a feed"
+            ),
+            error(
+                "operators[1]",
+                "duplicate of feeds[0].operators[0] `o-9q9-bart`"
+            ),
+        ])
+    );
+}
+
+#[test]
+fn validate_onestop_ids_and_urls() {
+    let registry = Registry::from_slice(
+        br#"{
+            "feeds": [
+                { "id": "bart", "spec": "gtfs", "urls": { "static_current": "not a url" } },
+                { "id": "f-bikes", "spec": "gbfs", "urls": {} }
+            ],
+            "operators": [{ "onestop_id": "o-" }]
+        }"#,
+    )
+    .unwrap();
+    assert_eq!(
+        registry.validate(),
+        Err(vec![
+            error(
+                "feeds[0].id",
+                "`bart` is not a valid OnestopID starting with `f-`"
+            ),
+            error(
+                "feeds[0].urls.static_current",
+                "`not a url` is not a valid URL"
+            ),
+            error("feeds[1].urls", "no URLs for a gbfs feed"),
+            error(
+                "operators[0].onestop_id",
+                "`o-` is not a valid OnestopID starting with `o-`"
+            ),
+        ])
+    );
+}
+
+#[test]
+fn merge() {
+    let mut registry = fixture("bart.dmfr.json");
+    let other = Registry::from_slice(
+        br#"{
+            "feeds": [
+                {
+                    "id": "f-9q9-actransit",
+                    "spec": "gtfs",
+                    "urls": { "static_current": "https://api.actransit.org/gtfs.zip" }
+                },
+                {
+                    "id": "f-9q9-bart",
+                    "spec": "gtfs",
+                    "name": "Bay Area Rapid Transit",
+                    "urls": { "static_current": "https://example.com/bart.zip" }
+                }
+            ],
+            "operators": [{
+                "onestop_id": "o-9q9-bayarea~511",
+                "associated_feeds": [{ "feed_onestop_id": "f-9q9-actransit" }]
+            }],
+            "license_spdx_identifier": "CC0-1.0"
+        }"#,
+    )
+    .unwrap();
+    registry.merge(other);
+    let ids: Vec<_> = registry.feeds.iter().map(|feed| feed.id.as_str()).collect();
+    assert_eq!(ids, ["f-9q9-bart", "f-9q9-bart~rt", "f-9q9-actransit"]);
+    assert_eq!(
+        registry.feeds[0].name.as_deref(),
+        Some("Bay Area Rapid Transit")
+    );
+    assert!(registry.feeds[0].operators.is_empty());
+    assert_eq!(registry.operators.len(), 1);
+    assert_eq!(
+        registry.operators[0].associated_feeds[0]
+            .feed_onestop_id
+            .as_deref(),
+        Some("f-9q9-actransit")
+    );
+    assert_eq!(registry.license_spdx_identifier.as_deref(), Some("CC0-1.0"));
+    assert!(registry.schema.is_some());
+    assert_eq!(registry.validate(), Ok(()));
+}
@@ -0,0 +1,37 @@
+/// A feed with a vehicle position and a trip update, encoded by hand from the
+/// field numbers of the GTFS Realtime specification.
+#[rustfmt::skip]
+const FEED: &[u8] = &[
+    0x0a, 0x0d, // header
+        0x0a, 0x03, b'2', b'.', b'0', // gtfs_realtime_version
+        0x10, 0x00, // incrementality: FULL_DATASET
+        0x18, 0x80, 0xe9, 0xc1, 0xa0, 0x06, // timestamp: 1678800000
+    0x12, 0x3f, // entity
+        0x0a, 0x01, b'1', // id
+        0x22, 0x3a, // vehicle
+            0x0a, 0x13, // trip
+                0x0a, 0x04, b'1', b'2', b'3', b'4', // trip_id
+                0x1a, 0x08, b'2', b'0', b'2', b'3', b'0', b'3', b'1', b'4', // start_date
+                0x2a, 0x01, b'1', // route_id
+            0x12, 0x0a, // position
+                0x0d, 0xe5, 0x50, 0x17, 0x42, // latitude: 37.829
+                0x15, 0xb4, 0x88, 0xf4, 0xc2, // longitude: -122.267
+            0x20, 0x01, // current_status: STOPPED_AT
+            0x28, 0x80, 0xe9, 0xc1, 0xa0, 0x06, // timestamp: 1678800000
+            0x3a, 0x04, b'M', b'C', b'A', b'R', // stop_id
+            0x42, 0x09, // vehicle
+                0x0a, 0x02, b'v', b'1', // id
+                0x12, 0x03, b'1', b'0', b'1', // label
+    0x12, 0x1d, // entity
+        0x0a, 0x01, b'2', // id
+        0x1a, 0x18, // trip_update
+            0x0a, 0x06, // trip
+                0x0a, 0x04, b'1', b'2', b'3', b'4', // trip_id
+            0x12, 0x0c, // stop_time_update
+                0x08, 0x0c, // stop_sequence: 12
+                0x12, 0x02, // arrival
+                    0x08, 0x5a, // delay: 90
+                0x22, 0x04, b'M', b'C', b'A', b'R', // stop_id
+            0x28, 0x5a, // delay: 90
+];
+
@@ -0,0 +1,94 @@
+use serde_json::json;
+use transitworld::data::{ExactTimes, ServiceTime, Trip};
+
+fn trip(frequencies: serde_json::Value) -> Trip {
+    let stop_time = |sequence: u64, arrival: &str, departure: &str| {
+        json!({
+            "arrival_time": arrival,
+            "departure_time": departure,
+            "stop_sequence": sequence,
+            "stop_headsign": "",
+            "pickup_type": 0,
+            "drop_off_type": 0,
+            "timepoint": 1,
+            "shape_dist_traveled": 0.0,
+            "interpolated": 0
+        })
+    };
+    serde_json::from_value(json!({
+        "id": 100,
+        "trip_id": "loop",
+        "trip_headsign": null,
+        "trip_short_name": null,
+        "direction_id": 0,
+        "block_id": null,
+        "wheelchair_accessible": null,
+        "bikes_allowed": null,
+        "stop_pattern_id": null,
+        "stop_times": [
+            stop_time(1, "00:00:00", "00:00:00"),
+            stop_time(2, "00:05:00", "00:06:00"),
+        ],
+        "shape": { "shape_id": "loop", "generated": false },
+        "calendar": {
+            "service_id": "daily",
+            "start_date": "2023-01-01",
+            "end_date": "2023-12-31",
+            "added_dates": null,
+            "removed_dates": null,
+            "generated": false,
+            "monday": 1,
+            "tuesday": 1,
+            "wednesday": 1,
+            "thursday": 1,
+            "friday": 1,
+            "saturday": 1,
+            "sunday": 1
+        },
+        "frequencies": frequencies,
+        "route": null,
+        "feed_version": {
+            "id": 7,
+            "sha1": "e535eb2b3b8e2bcd0ac6d1e5d6f95e6d0bd5d9f0",
+            "fetched_at": "2023-01-02T03:04:05Z",
+            "url": null,
+            "earliest_calendar_date": null,
+            "latest_calendar_date": null
+        }
+    }))
+    .unwrap()
+}
+
+fn time(time: &str) -> ServiceTime {
+    time.parse().unwrap()
+}
+
+#[test]
+fn expand_frequencies() {
+    let trip = trip(json!([
+        { "start_time": "23:30:00", "end_time": "24:30:00", "headway_secs": 1200, "exact_times": 1 },
+        { "start_time": "06:00:00", "end_time": "06:10:00", "headway_secs": 600, "exact_times": 0 },
+    ]));
+    let instances: Vec<_> = trip.expand_frequencies().collect();
+
+    let start_times: Vec<_> = instances.iter().map(|trip| trip.start_time).collect();
+    assert_eq!(
+        start_times,
+        ["23:30:00", "23:50:00", "24:10:00", "06:00:00"].map(time)
+    );
+    assert_eq!(instances[2].exact_times, ExactTimes::ScheduleBased);
+    assert_eq!(instances[3].exact_times, ExactTimes::FrequencyBased);
+
+    let last_stop = &instances[2].stop_times[1];
+    assert_eq!(last_stop.arrival_time, time("24:15:00"));
+    assert_eq!(last_stop.departure_time, time("24:16:00"));
+}
+
+#[test]
+fn expand_scheduled_trip() {
+    let trip = trip(json!([]));
+    let instances: Vec<_> = trip.expand_frequencies().collect();
+    assert_eq!(instances.len(), 1);
+    assert_eq!(instances[0].exact_times, ExactTimes::ScheduleBased);
+    assert_eq!(&instances[0].stop_times, trip.stop_times.as_ref().unwrap());
+}
@@ -0,0 +1,198 @@
+use std::io::{Cursor, Write};
+
+use chrono::NaiveDate;
+use transitworld::{
+    data::{Calendar, RouteType, ServiceTime},
+    gtfs::{Archive, RowError},
+    Error,
+};
+use zip::{write::FileOptions, ZipWriter};
+
+/// An archive holding the given files in memory.
+fn archive(files: &[(&str, &str)]) -> Archive {
+    let mut zip = ZipWriter::new(Cursor::new(vec![]));
+    for (name, contents) in files {
+        zip.start_file(*name, FileOptions::default()).unwrap();
+        zip.write_all(contents.as_bytes()).unwrap();
+    }
+    Archive::from_zip(zip.finish().unwrap()).unwrap()
+}
+
+fn date(day: u32) -> NaiveDate {
+    NaiveDate::from_ymd_opt(2023, 7, day).unwrap()
+}
+
+#[test]
+fn records() {
+    let mut archive = archive(&[
+        (
+            "routes.txt",
+            "route_id,route_short_name,route_type\n\
+             1, Red ,1\n\
+             2,,3\n",
+        ),
+        (
+            "stop_times.txt",
+            "trip_id,arrival_time,departure_time,stop_id,stop_sequence\n\
+             a,25:00:00,25:01:00,s1,1\n\
+             a,,,s2,2\n",
+        ),
+    ]);
+
+    let routes = archive
+        .routes()
+        .unwrap()
+        .collect::<Result<Vec<_>, _>>()
+        .unwrap();
+    assert_eq!(routes.len(), 2);
+    assert_eq!(routes[0].route_id, "1");
+    assert_eq!(routes[0].route_short_name.as_deref(), Some("Red"));
+    assert_eq!(routes[0].route_type, RouteType::Subway);
+    assert_eq!(routes[1].route_short_name, None);
+    assert_eq!(routes[1].route_type, RouteType::Bus);
+
+    let stop_times = archive
+        .stop_times()
+        .unwrap()
+        .collect::<Result<Vec<_>, _>>()
+        .unwrap();
+    assert_eq!(stop_times[0].arrival_time, ServiceTime::new(25, 0, 0));
+    assert_eq!(stop_times[1].arrival_time, None);
+    assert_eq!(stop_times[1].stop_sequence, 2);
+}
+
+#[test]
+fn row_errors() {
+    let mut archive = archive(&[(
+        "stop_times.txt",
+        "trip_id,arrival_time,departure_time,stop_id,stop_sequence\n\
+         a,08:00:00,08:00:00,s1,1\n\
+         a,8am,8am,s2,2\n\
+         a,08:10:00,08:10:00,s3,3\n",
+    )]);
+
+    let stop_times: Vec<_> = archive.stop_times().unwrap().collect();
+    assert_eq!(stop_times.len(), 3);
+    assert!(stop_times[0].is_ok());
+    let err: &RowError = stop_times[1].as_ref().unwrap_err();
+    assert_eq!(err.file, "stop_times.txt");
+    assert_eq!(err.line, Some(3));
+    assert!(err.message.contains("8am"), "{}", err.message);
+    assert_eq!(stop_times[2].as_ref().unwrap().stop_id, "s3");
+}
+
+#[test]
+fn missing_files() {
+    let mut archive = archive(&[("routes.txt", "route_id,route_type\n1,3\n")]);
+
+    assert!(matches!(
+        archive.agencies(),
+        Err(Error::MissingFile("agency.txt"))
+    ));
+    assert!(matches!(
+        archive.stops(),
+        Err(Error::MissingFile("stops.txt"))
+    ));
+    assert_eq!(archive.frequencies().unwrap().count(), 0);
+    assert_eq!(archive.shapes().unwrap().count(), 0);
+    assert_eq!(archive.calendars().unwrap(), (vec![], vec![]));
+}
+
+#[test]
+fn nested_files() {
+    let routes = |name: &str| format!("route_id,route_type\n{},3\n", name);
+    let first_route = |files: &[(&str, &str)]| {
+        let mut archive = archive(files);
+        let mut routes = archive.routes().unwrap();
+        routes.next().unwrap().unwrap().route_id
+    };
+
+    let (root, shallow, deep) = (routes("root"), routes("shallow"), routes("deep"));
+    assert_eq!(
+        first_route(&[
+            ("feed/nested/routes.txt", &deep),
+            ("feed/routes.txt", &shallow),
+        ]),
+        "shallow"
+    );
+    assert_eq!(
+        first_route(&[
+            ("feed/routes.txt", &shallow),
+            ("routes.txt", &root),
+            ("feed/nested/routes.txt", &deep),
+        ]),
+        "root"
+    );
+    // only whole file names match
+    assert!(matches!(
+        archive(&[("old_routes.txt", &root)]).routes(),
+        Err(Error::MissingFile("routes.txt"))
+    ));
+}
+
+#[test]
+fn calendars() {
+    let mut archive = archive(&[
+        (
+            "calendar.txt",
+            "service_id,monday,tuesday,wednesday,thursday,friday,saturday,sunday,start_date,end_date\n\
+             weekday,1,1,1,1,1,0,0,20230701,20230731\n\
+             broken,1,1,1,1,1,0,0,July,20230731\n",
+        ),
+        (
+            "calendar_dates.txt",
+            "service_id,date,exception_type\n\
+             weekday,20230704,2\n\
+             weekday,20230801,1\n\
+             holiday,20230904,1\n\
+             holiday,20230704,1\n\
+             strike,20230705,3\n",
+        ),
+    ]);
+
+    let (calendars, errors) = archive.calendars().unwrap();
+    // no calendar is generated for the service of the invalid row
+    assert_eq!(
+        calendars,
+        vec![
+            Calendar {
+                service_id: Some("holiday".into()),
+                start_date: date(4),
+                end_date: NaiveDate::from_ymd_opt(2023, 9, 4).unwrap(),
+                added_dates: Some(vec![NaiveDate::from_ymd_opt(2023, 9, 4).unwrap(), date(4)]),
+                removed_dates: Some(vec![]),
+                generated: Some(true),
+                monday: 0,
+                tuesday: 0,
+                wednesday: 0,
+                thursday: 0,
+                friday: 0,
+                saturday: 0,
+                sunday: 0,
+            },
+            Calendar {
+                service_id: Some("weekday".into()),
+                start_date: date(1),
+                end_date: date(31),
+                added_dates: Some(vec![NaiveDate::from_ymd_opt(2023, 8, 1).unwrap()]),
+                removed_dates: Some(vec![date(4)]),
+                generated: Some(false),
+                monday: 1,
+                tuesday: 1,
+                wednesday: 1,
+                thursday: 1,
+                friday: 1,
+                saturday: 0,
+                sunday: 0,
+            },
+        ]
+    );
+
+    assert_eq!(errors.len(), 2);
+    assert_eq!(errors[0].file, "calendar.txt");
+    assert_eq!(errors[0].line, Some(3));
+    assert_eq!(errors[1].file, "calendar_dates.txt");
+    assert_eq!(errors[1].line, Some(6));
+    assert!(errors[1].message.contains("exception_type 3"));
+    assert!(errors[1].message.contains("strike"));
+}
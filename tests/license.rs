@@ -0,0 +1,151 @@
+use serde_json::json;
+use transitworld::{
+    data::{Feed, License, Permission},
+    license::{LicensePolicy, LicenseStatus, LicenseUse},
+};
+
+fn feed(name: Option<&str>, license: License) -> Feed {
+    let mut feed: Feed = serde_json::from_value(json!({
+        "id": 1,
+        "onestop_id": "f-9q9-bart",
+        "name": name,
+        "spec": "gtfs",
+        "feed_namespace_id": null,
+        "associated_feeds": null,
+        "languages": null,
+        "urls": {},
+        "license": {},
+        "authorization": {},
+        "geometry": null,
+        "feed_state": { "feed_version": null },
+        "feed_versions": []
+    }))
+    .unwrap();
+    feed.license = license;
+    feed
+}
+
+fn license() -> License {
+    License {
+        spdx_identifier: Some("CC-BY-4.0".into()),
+        use_without_attribution: Permission::No,
+        commercial_use_allowed: Permission::Yes,
+        redistribution_allowed: Permission::No,
+        attribution_text: Some("Data from BART".into()),
+        attribution_instructions: Some("Display on every map".into()),
+        ..Default::default()
+    }
+}
+
+#[test]
+fn status() {
+    let check = LicensePolicy::new().check_license(&license());
+    assert_eq!(check.status, LicenseStatus::Allowed);
+    assert!(check.is_allowed());
+    assert!(check.forbidden.is_empty() && check.unknown.is_empty());
+
+    let check = LicensePolicy::new()
+        .with_commercial_use(true)
+        .check_license(&license());
+    assert!(check.is_allowed());
+
+    let check = LicensePolicy::new()
+        .with_commercial_use(true)
+        .with_derived_product(true)
+        .check_license(&license());
+    assert_eq!(check.status, LicenseStatus::Unknown);
+    assert_eq!(check.unknown, [LicenseUse::DerivedProduct]);
+
+    let check = LicensePolicy::new()
+        .with_derived_product(true)
+        .with_redistribution(true)
+        .with_private_modifications(true)
+        .check_license(&license());
+    assert_eq!(check.status, LicenseStatus::Forbidden);
+    assert_eq!(check.forbidden, [LicenseUse::Redistribution]);
+    assert_eq!(
+        check.unknown,
+        [LicenseUse::DerivedProduct, LicenseUse::PrivateModifications]
+    );
+}
+
+#[test]
+fn uses_can_be_unset() {
+    let policy = LicensePolicy::new()
+        .with_redistribution(true)
+        .with_redistribution(false);
+    assert_eq!(policy, LicensePolicy::new());
+    assert!(policy.check_license(&license()).is_allowed());
+}
+
+#[test]
+fn attribution() {
+    let check = LicensePolicy::new().check_license(&license());
+    assert!(check.attribution_required);
+    assert_eq!(check.attribution_text.as_deref(), Some("Data from BART"));
+    assert_eq!(
+        check.attribution_instructions.as_deref(),
+        Some("Display on every map")
+    );
+
+    // attribution is still required when the policy intends to omit it
+    let check = LicensePolicy::new()
+        .without_attribution(true)
+        .check_license(&license());
+    assert_eq!(check.status, LicenseStatus::Forbidden);
+    assert_eq!(check.forbidden, [LicenseUse::UseWithoutAttribution]);
+    assert!(check.attribution_required);
+    assert_eq!(check.attribution_text.as_deref(), Some("Data from BART"));
+
+    // and when the license has not been reviewed
+    let unknown = License {
+        use_without_attribution: Permission::Unknown,
+        ..license()
+    };
+    assert!(
+        LicensePolicy::new()
+            .check_license(&unknown)
+            .attribution_required
+    );
+
+    let not_required = License {
+        use_without_attribution: Permission::Yes,
+        ..license()
+    };
+    let check = LicensePolicy::new()
+        .without_attribution(true)
+        .check_license(&not_required);
+    assert!(check.is_allowed());
+    assert!(!check.attribution_required);
+    assert_eq!(check.attribution_text, None);
+    assert_eq!(check.attribution_instructions, None);
+}
+
+#[test]
+fn check_feed() {
+    let policy = LicensePolicy::new();
+    let check = policy.check(&feed(Some("BART"), license()));
+    assert_eq!(check, policy.check_license(&license()));
+
+    let without_text = License {
+        attribution_text: None,
+        ..license()
+    };
+    let check = policy.check(&feed(Some("BART"), without_text.clone()));
+    assert_eq!(check.attribution_text.as_deref(), Some("BART (CC-BY-4.0)"));
+
+    let without_spdx = License {
+        spdx_identifier: None,
+        ..without_text.clone()
+    };
+    let check = policy.check(&feed(None, without_spdx));
+    assert_eq!(check.attribution_text.as_deref(), Some("f-9q9-bart"));
+
+    let not_required = License {
+        use_without_attribution: Permission::Yes,
+        ..without_text
+    };
+    let check = policy.check(&feed(Some("BART"), not_required));
+    assert!(!check.attribution_required);
+    assert_eq!(check.attribution_text, None);
+}
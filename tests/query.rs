@@ -0,0 +1,202 @@
+use chrono::NaiveDate;
+use transitworld::{
+    data::{LocationType, RouteType, Spec},
+    query::{
+        AgencyQuery, FeedQuery, FeedVersionQuery, GeoFilter, OperatorQuery, QueryParams,
+        RouteQuery, StopQuery, TripQuery,
+    },
+    Error,
+};
+
+fn is_invalid<T: std::fmt::Debug>(result: transitworld::Result<T>) -> bool {
+    matches!(result, Err(Error::InvalidArgument(_)))
+}
+
+#[test]
+fn radius() {
+    let GeoFilter::Radius(radius) = GeoFilter::radius(37.829, -122.267, 500.0).unwrap() else {
+        panic!("expected a radius");
+    };
+    assert_eq!(
+        (radius.lat(), radius.lon(), radius.radius()),
+        (37.829, -122.267, 500.0)
+    );
+    assert!(GeoFilter::radius(90.0, 180.0, 1.0).is_ok());
+
+    assert!(is_invalid(GeoFilter::radius(90.5, 0.0, 1.0)));
+    assert!(is_invalid(GeoFilter::radius(-91.0, 0.0, 1.0)));
+    assert!(is_invalid(GeoFilter::radius(0.0, 180.5, 1.0)));
+    assert!(is_invalid(GeoFilter::radius(0.0, -181.0, 1.0)));
+    assert!(is_invalid(GeoFilter::radius(f64::NAN, 0.0, 1.0)));
+    assert!(is_invalid(GeoFilter::radius(0.0, f64::NAN, 1.0)));
+}
+
+#[test]
+fn radius_must_be_positive_and_finite() {
+    for radius in [0.0, -1.0, f64::NAN, f64::INFINITY] {
+        assert!(
+            is_invalid(GeoFilter::radius(0.0, 0.0, radius)),
+            "{}",
+            radius
+        );
+    }
+}
+
+#[test]
+fn bbox() {
+    let GeoFilter::BoundingBox(bbox) = GeoFilter::bbox(-122.5, 37.5, -122.0, 38.0).unwrap() else {
+        panic!("expected a bounding box");
+    };
+    assert_eq!(
+        (
+            bbox.min_lon(),
+            bbox.min_lat(),
+            bbox.max_lon(),
+            bbox.max_lat()
+        ),
+        (-122.5, 37.5, -122.0, 38.0)
+    );
+    // a bounding box may be a single point
+    assert!(GeoFilter::bbox(-122.0, 37.5, -122.0, 37.5).is_ok());
+
+    assert!(is_invalid(GeoFilter::bbox(-122.0, 37.5, -122.5, 38.0)));
+    assert!(is_invalid(GeoFilter::bbox(-122.5, 38.0, -122.0, 37.5)));
+    assert!(is_invalid(GeoFilter::bbox(-181.0, 37.5, -122.0, 38.0)));
+    assert!(is_invalid(GeoFilter::bbox(-122.5, -90.5, -122.0, 38.0)));
+    assert!(is_invalid(GeoFilter::bbox(-122.5, 37.5, 180.5, 38.0)));
+    assert!(is_invalid(GeoFilter::bbox(-122.5, 37.5, -122.0, f64::NAN)));
+}
+
+fn params(query: &impl QueryParams) -> Vec<(&'static str, String)> {
+    query.params()
+}
+
+#[test]
+fn empty_queries() {
+    assert!(params(&FeedQuery::default()).is_empty());
+    assert!(params(&FeedVersionQuery::default()).is_empty());
+    assert!(params(&AgencyQuery::default()).is_empty());
+    assert!(params(&OperatorQuery::default()).is_empty());
+    assert!(params(&RouteQuery::default()).is_empty());
+    assert!(params(&StopQuery::default()).is_empty());
+    assert!(params(&TripQuery::default()).is_empty());
+}
+
+#[test]
+fn feed_query() {
+    let query = FeedQuery {
+        spec: Some(Spec::GTFSRealtime),
+        fetch_error: Some(false),
+        tag_key: Some("unstable_url".into()),
+        tag_value: Some("true".into()),
+    };
+    assert_eq!(
+        params(&query),
+        [
+            ("spec", "gtfs-rt".to_owned()),
+            ("fetch_error", "false".to_owned()),
+            ("tag_key", "unstable_url".to_owned()),
+            ("tag_value", "true".to_owned()),
+        ]
+    );
+}
+
+#[test]
+fn agency_and_operator_queries() {
+    let query = AgencyQuery {
+        agency_name: Some("BART".into()),
+        feed_onestop_id: Some("f-9q9-bart".into()),
+        ..Default::default()
+    };
+    assert_eq!(
+        params(&query),
+        [
+            ("agency_name", "BART".to_owned()),
+            ("feed_onestop_id", "f-9q9-bart".to_owned()),
+        ]
+    );
+
+    let query = OperatorQuery {
+        adm0_name: Some("United States of America".into()),
+        city_name: Some("Oakland".into()),
+        ..Default::default()
+    };
+    assert_eq!(
+        params(&query),
+        [
+            ("adm0_name", "United States of America".to_owned()),
+            ("city_name", "Oakland".to_owned()),
+        ]
+    );
+}
+
+#[test]
+fn route_query() {
+    let query = RouteQuery {
+        route_type: Some(RouteType::Subway),
+        operator_onestop_id: Some("o-9q9-bart".into()),
+        agency_id: Some(12),
+    };
+    assert_eq!(
+        params(&query),
+        [
+            ("route_type", "1".to_owned()),
+            ("operator_onestop_id", "o-9q9-bart".to_owned()),
+            ("agency_id", "12".to_owned()),
+        ]
+    );
+
+    let query = RouteQuery {
+        route_type: Some(RouteType::Extended(715)),
+        ..Default::default()
+    };
+    assert_eq!(params(&query), [("route_type", "715".to_owned())]);
+}
+
+#[test]
+fn stop_query() {
+    let query = StopQuery {
+        served_by_onestop_ids: vec!["r-9q9-antioch~sfia~millbrae".into(), "o-9q9-bart".into()],
+        location_type: Some(LocationType::Station),
+    };
+    assert_eq!(
+        params(&query),
+        [
+            (
+                "served_by_onestop_ids",
+                "r-9q9-antioch~sfia~millbrae,o-9q9-bart".to_owned()
+            ),
+            ("location_type", "1".to_owned()),
+        ]
+    );
+
+    let query = StopQuery {
+        served_by_onestop_ids: vec!["o-9q9-bart".into()],
+        ..Default::default()
+    };
+    assert_eq!(
+        params(&query),
+        [("served_by_onestop_ids", "o-9q9-bart".to_owned())]
+    );
+}
+
+#[test]
+fn trip_query() {
+    let query = TripQuery {
+        trip_id: Some("1234".into()),
+        service_date: NaiveDate::from_ymd_opt(2023, 3, 4),
+    };
+    assert_eq!(
+        params(&query),
+        [
+            ("trip_id", "1234".to_owned()),
+            ("service_date", "2023-03-04".to_owned()),
+        ]
+    );
+
+    let query = FeedVersionQuery {
+        sha1: Some("e535eb2b".into()),
+        ..Default::default()
+    };
+    assert_eq!(params(&query), [("sha1", "e535eb2b".to_owned())]);
+}
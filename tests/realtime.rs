@@ -0,0 +1,126 @@
+#![cfg(feature = "realtime")]
+
+use prost::Message;
+use transitworld::realtime::{
+    Alert, FeedEntity, FeedHeader, FeedMessage, Incrementality, Position, TranslatedString,
+    Translation, TripDescriptor, TripUpdate, VehiclePosition, VehicleStopStatus,
+};
+
+include!("fixtures/realtime_feed.rs");
+
+fn trip() -> TripDescriptor {
+    TripDescriptor {
+        trip_id: Some("1234".into()),
+        route_id: Some("1".into()),
+        ..Default::default()
+    }
+}
+
+#[test]
+fn feed_message_round_trip() {
+    let message = FeedMessage {
+        header: FeedHeader {
+            gtfs_realtime_version: "2.0".into(),
+            timestamp: Some(1_678_800_000),
+            ..Default::default()
+        },
+        entity: vec![
+            FeedEntity {
+                id: "vehicle".into(),
+                vehicle: Some(VehiclePosition {
+                    trip: Some(trip()),
+                    position: Some(Position {
+                        latitude: 37.829,
+                        longitude: -122.267,
+                        ..Default::default()
+                    }),
+                    ..Default::default()
+                }),
+                ..Default::default()
+            },
+            FeedEntity {
+                id: "update".into(),
+                trip_update: Some(TripUpdate {
+                    trip: trip(),
+                    delay: Some(90),
+                    ..Default::default()
+                }),
+                ..Default::default()
+            },
+            FeedEntity {
+                id: "alert".into(),
+                alert: Some(Alert {
+                    header_text: Some(TranslatedString {
+                        translation: vec![
+                            Translation {
+                                text: "Delays".into(),
+                                language: Some("en".into()),
+                            },
+                            Translation {
+                                text: "Retrasos".into(),
+                                language: Some("es".into()),
+                            },
+                        ],
+                    }),
+                    ..Default::default()
+                }),
+                ..Default::default()
+            },
+        ],
+    };
+
+    let decoded = FeedMessage::decode(message.encode_to_vec().as_slice()).unwrap();
+    assert_eq!(decoded, message);
+
+    assert_eq!(decoded.vehicle_positions().count(), 1);
+    assert_eq!(decoded.trip_updates().next().unwrap().delay, Some(90));
+    let header = decoded
+        .alerts()
+        .next()
+        .unwrap()
+        .header_text
+        .as_ref()
+        .unwrap();
+    assert_eq!(header.get(Some("es")), Some("Retrasos"));
+}
+
+#[test]
+fn decode_fixture() {
+    let message = FeedMessage::decode(FEED).unwrap();
+    assert_eq!(message.header.gtfs_realtime_version, "2.0");
+    assert_eq!(message.header.incrementality(), Incrementality::FullDataset);
+    assert_eq!(message.header.timestamp, Some(1_678_800_000));
+    assert_eq!(message.entity.len(), 2);
+
+    let vehicle = message.vehicle_positions().next().unwrap();
+    let trip = vehicle.trip.as_ref().unwrap();
+    assert_eq!(trip.trip_id.as_deref(), Some("1234"));
+    assert_eq!(trip.route_id.as_deref(), Some("1"));
+    assert_eq!(trip.start_date.as_deref(), Some("20230314"));
+    let position = vehicle.position.as_ref().unwrap();
+    assert_eq!(position.latitude, 37.829);
+    assert_eq!(position.longitude, -122.267);
+    assert_eq!(vehicle.current_status(), VehicleStopStatus::StoppedAt);
+    assert_eq!(vehicle.timestamp, Some(1_678_800_000));
+    assert_eq!(vehicle.stop_id.as_deref(), Some("MCAR"));
+    let descriptor = vehicle.vehicle.as_ref().unwrap();
+    assert_eq!(descriptor.id.as_deref(), Some("v1"));
+    assert_eq!(descriptor.label.as_deref(), Some("101"));
+
+    let update = message.trip_updates().next().unwrap();
+    assert_eq!(update.trip.trip_id.as_deref(), Some("1234"));
+    assert_eq!(update.delay, Some(90));
+    let stop_time_update = &update.stop_time_update[0];
+    assert_eq!(stop_time_update.stop_sequence, Some(12));
+    assert_eq!(stop_time_update.stop_id.as_deref(), Some("MCAR"));
+    assert_eq!(stop_time_update.arrival.as_ref().unwrap().delay, Some(90));
+    assert_eq!(stop_time_update.departure, None);
+
+    // and encodes back to the same bytes
+    assert_eq!(message.encode_to_vec(), FEED);
+}
+
+#[test]
+fn decode_invalid() {
+    assert!(FeedMessage::decode(&b"\xff\xff\xff"[..]).is_err());
+}
@@ -0,0 +1,357 @@
+use std::fmt::Debug;
+
+use serde::{de::DeserializeOwned, Serialize};
+use serde_json::{json, Value};
+use transitworld::data::{
+    partial, Agency, Calendar, Departure, Feed, FeedVersion, Operator, Permission, Route, Stop,
+    StopDepartures, Trip,
+};
+
+/// Deserialize `json`, then check that serializing and deserializing the
+/// result again produces an equal value.
+fn round_trip<T>(json: Value) -> T
+where
+    T: Serialize + DeserializeOwned + PartialEq + Debug,
+{
+    let value: T = serde_json::from_value(json).expect("failed to deserialize fixture");
+    let serialized = serde_json::to_string(&value).expect("failed to serialize");
+    let deserialized: T = serde_json::from_str(&serialized).expect("failed to deserialize");
+    assert_eq!(value, deserialized);
+    value
+}
+
+fn partial_feed_version() -> Value {
+    json!({
+        "id": 7,
+        "sha1": "e535eb2b3b8e2bcd0ac6d1e5d6f95e6d0bd5d9f0",
+        "fetched_at": "2023-01-02T03:04:05Z",
+        "url": "https://example.com/gtfs.zip",
+        "earliest_calendar_date": "2023-01-01",
+        "latest_calendar_date": "2023-12-31"
+    })
+}
+
+fn calendar() -> Value {
+    json!({
+        "service_id": "weekday",
+        "start_date": "2023-01-01",
+        "end_date": "2023-12-31",
+        "added_dates": ["2023-07-04"],
+        "removed_dates": ["2023-12-25"],
+        "generated": false,
+        "monday": 1,
+        "tuesday": 1,
+        "wednesday": 1,
+        "thursday": 1,
+        "friday": 1,
+        "saturday": 0,
+        "sunday": 0
+    })
+}
+
+fn stop_time() -> Value {
+    json!({
+        "arrival_time": 28800,
+        "departure_time": 28830,
+        "stop_sequence": 1,
+        "stop_headsign": "Downtown",
+        "pickup_type": 0,
+        "drop_off_type": 0,
+        "timepoint": 1,
+        "shape_dist_traveled": 1.25,
+        "interpolated": 0
+    })
+}
+
+fn partial_stop() -> Value {
+    json!({
+        "id": 12,
+        "onestop_id": "s-9q9p1wxf72-macarthur",
+        "stop_id": "MCAR",
+        "stop_name": "MacArthur",
+        "stop_code": null,
+        "stop_timezone": "America/Los_Angeles",
+        "geometry": { "type": "Point", "coordinates": [-122.267, 37.829] }
+    })
+}
+
+#[test]
+fn feed() {
+    let feed: Feed = round_trip(json!({
+        "id": 1,
+        "onestop_id": "f-9q9-bart",
+        "name": "BART",
+        "spec": "gtfs",
+        "feed_namespace_id": null,
+        "associated_feeds": ["f-9q9-bart~rt"],
+        "languages": ["en"],
+        "urls": {
+            "static_current": "https://www.bart.gov/dev/schedules/google_transit.zip",
+            "static_historic": ["https://example.com/old.zip"],
+            "realtime_alerts": "https://api.bart.gov/gtfsrt/alerts.aspx"
+        },
+        "license": {
+            "spdx_identifier": "CC-BY-4.0",
+            "use_without_attribution": "no",
+            "commercial_use_allowed": "yes"
+        },
+        "authorization": {
+            "type": "query_param",
+            "param_name": "key",
+            "info_url": "https://example.com/signup"
+        },
+        "geometry": {
+            "type": "Polygon",
+            "coordinates": [[[-122.5, 37.5], [-122.0, 37.5], [-122.0, 38.0], [-122.5, 37.5]]]
+        },
+        "feed_state": {
+            "last_fetch_error": "",
+            "last_fetched_at": "2023-01-02T03:04:05",
+            "last_successful_fetch_at": "2023-01-02T03:04:05",
+            "feed_version": partial_feed_version()
+        },
+        "feed_versions": [partial_feed_version()]
+    }));
+    assert_eq!(feed.feed_versions.len(), 1);
+    assert_eq!(feed.license.use_without_attribution, Permission::No);
+    assert_eq!(feed.license.redistribution_allowed, Permission::Unknown);
+}
+
+#[test]
+fn feed_version() {
+    round_trip::<FeedVersion>(json!({
+        "id": 7,
+        "sha1": "e535eb2b3b8e2bcd0ac6d1e5d6f95e6d0bd5d9f0",
+        "fetched_at": "2023-01-02T03:04:05",
+        "url": "https://example.com/gtfs.zip",
+        "earliest_calendar_date": "2023-01-01",
+        "latest_calendar_date": "2023-12-31",
+        "files": [{
+            "name": "stops.txt",
+            "sha1": "da39a3ee5e6b4b0d3255bfef95601890afd80709",
+            "header": "stop_id,stop_name,stop_lat,stop_lon",
+            "rows": 50,
+            "csv_like": true,
+            "size": 2048
+        }],
+        "service_levels": [calendar()],
+        "feed": { "name": null, "onestop_id": "f-9q9-bart", "spec": "gtfs" }
+    }));
+}
+
+#[test]
+fn agency() {
+    round_trip::<Agency>(json!({
+        "id": 3,
+        "onestop_id": "o-9q9-bart",
+        "agency_id": "BART",
+        "agency_name": "Bay Area Rapid Transit",
+        "agency_url": "https://www.bart.gov",
+        "agency_timezone": "America/Los_Angeles",
+        "agency_lang": "en",
+        "agency_phone": null,
+        "agency_fare_url": null,
+        "agency_email": null,
+        "geometry": null,
+        "operator": {
+            "onestop_id": "o-9q9-bart",
+            "name": "BART",
+            "short_name": null,
+            "website": null,
+            "tags": { "twitter_general": "sfbart" }
+        },
+        "places": [{ "city_name": "Oakland", "adm1_name": "California", "adm0_name": "United States" }],
+        "feed_version": partial_feed_version(),
+        "routes": [{ "id": 5, "route_id": "1", "route_long_name": "Yellow", "route_short_name": null }]
+    }));
+}
+
+#[test]
+fn operator() {
+    round_trip::<Operator>(json!({
+        "id": 4,
+        "onestop_id": "o-9q9-bart",
+        "name": "Bay Area Rapid Transit",
+        "short_name": "BART",
+        "website": "https://www.bart.gov",
+        "tags": { "us_ntd_id": "90003" },
+        "agencies": [{ "id": 3, "agency_id": "BART", "agency_name": "BART", "places": null }]
+    }));
+}
+
+#[test]
+fn route() {
+    let route: Route = round_trip(json!({
+        "id": 5,
+        "onestop_id": "r-9q9-yellow",
+        "route_id": "1",
+        "route_type": 1,
+        "route_short_name": null,
+        "route_long_name": "Antioch - SFIA/Millbrae",
+        "route_color": "ffff33",
+        "route_text_color": "000000",
+        "route_sort_order": 0,
+        "agency": { "id": 3, "agency_id": "BART", "agency_name": "BART", "places": null },
+        "feed_version": partial_feed_version(),
+        "route_stops": [{ "stop": partial_stop() }]
+    }));
+    let route_stops = route.route_stops.as_ref().unwrap();
+    assert_eq!(route_stops.len(), 1);
+    assert_eq!(route_stops[0].stop.stop_id.as_deref(), Some("MCAR"));
+
+    // route stops keep their wire format when serialized
+    let value = serde_json::to_value(&route).unwrap();
+    assert_eq!(value["route_stops"][0]["stop"]["id"], 12);
+}
+
+#[test]
+fn stop() {
+    round_trip::<Stop>(json!({
+        "id": 12,
+        "onestop_id": "s-9q9p1wxf72-macarthur",
+        "stop_id": "MCAR",
+        "stop_name": "MacArthur",
+        "stop_desc": null,
+        "stop_url": null,
+        "stop_timezone": null,
+        "stop_code": null,
+        "zone_id": "MCAR",
+        "wheelchair_boarding": 1,
+        "location_type": 0,
+        "feed_version": { "id": 7, "sha1": "e535eb2b3b8e2bcd0ac6d1e5d6f95e6d0bd5d9f0" },
+        "level": { "level_id": "L1", "level_name": "Platform", "level_index": "1" },
+        "route_stops": [{ "route": { "id": 5, "route_id": "1" } }],
+        "geometry": { "type": "Point", "coordinates": [-122.267, 37.829] }
+    }));
+}
+
+#[test]
+fn trip() {
+    round_trip::<Trip>(json!({
+        "id": 100,
+        "trip_id": "1234",
+        "trip_headsign": "Antioch",
+        "trip_short_name": null,
+        "direction_id": 0,
+        "block_id": null,
+        "wheelchair_accessible": 1,
+        "bikes_allowed": 1,
+        "stop_pattern_id": 2,
+        "stop_times": [stop_time()],
+        "shape": { "shape_id": "shape-1", "generated": false },
+        "calendar": calendar(),
+        "frequencies": [{ "start_time": 21600, "end_time": 36000, "headway_secs": 600, "exact_times": 0 }],
+        "route": { "id": 5, "route_id": "1", "route_long_name": null, "route_short_name": "Y" },
+        "feed_version": partial_feed_version()
+    }));
+}
+
+#[test]
+fn calendar_generated() {
+    let calendar: Calendar = round_trip(json!({
+        "service_id": null,
+        "start_date": "2023-07-04",
+        "end_date": "2023-07-04",
+        "added_dates": null,
+        "removed_dates": null,
+        "generated": true,
+        "monday": 0,
+        "tuesday": 0,
+        "wednesday": 0,
+        "thursday": 0,
+        "friday": 0,
+        "saturday": 0,
+        "sunday": 0
+    }));
+    assert_eq!(calendar.generated, Some(true));
+}
+
+#[test]
+fn stop_departures() {
+    let mut departure = stop_time();
+    departure["service_date"] = json!("2023-03-14");
+    departure["arrival"] = json!({
+        "scheduled": "08:00:00",
+        "estimated": "08:01:30",
+        "estimated_utc": "2023-03-14T15:01:30Z",
+        "delay": 90,
+        "uncertainty": 30
+    });
+    departure["departure"] = Value::Null;
+    departure["trip"] = json!({
+        "id": 100,
+        "trip_id": "1234",
+        "trip_headsign": "Antioch",
+        "trip_short_name": null,
+        "direction_id": 0,
+        "block_id": null,
+        "route": null
+    });
+    round_trip::<Departure>(departure.clone());
+
+    let mut stop = partial_stop();
+    stop["departures"] = json!([departure]);
+    let stop: StopDepartures = round_trip(stop);
+    assert_eq!(stop.departures.len(), 1);
+}
+
+#[test]
+fn partial_types() {
+    round_trip::<partial::FeedVersion>(partial_feed_version());
+    round_trip::<partial::Stop>(partial_stop());
+    round_trip::<partial::Feed>(
+        json!({ "name": "BART", "onestop_id": "f-9q9-bart", "spec": "gtfs-rt" }),
+    );
+}
+
+#[test]
+fn gtfs_enums() {
+    use transitworld::data::{LocationType, RouteType};
+
+    let types: Vec<RouteType> = round_trip(json!([3, 700, 9999]));
+    assert_eq!(
+        types,
+        [
+            RouteType::Bus,
+            RouteType::Extended(700),
+            RouteType::Unknown(9999),
+        ]
+    );
+    assert_eq!(types[1].basic(), Some(RouteType::Bus));
+    assert_eq!(serde_json::to_value(types).unwrap(), json!([3, 700, 9999]));
+
+    // extended route types range from 100 to 1700
+    let bounds: Vec<RouteType> = round_trip(json!([99, 100, 1700, 1701]));
+    assert_eq!(
+        bounds,
+        [
+            RouteType::Unknown(99),
+            RouteType::Extended(100),
+            RouteType::Extended(1700),
+            RouteType::Unknown(1701),
+        ]
+    );
+
+    let location_type: LocationType = round_trip(json!(7));
+    assert_eq!(location_type, LocationType::Unknown(7));
+}
+
+#[test]
+fn service_time() {
+    use chrono::{FixedOffset, NaiveDate, TimeZone};
+    use transitworld::data::ServiceTime;
+
+    let times: Vec<ServiceTime> = round_trip(json!([28800, "8:00:00", "25:30:05"]));
+    assert_eq!(times[0], times[1]);
+    assert_eq!(times[2], ServiceTime::new(25, 30, 5).unwrap());
+    assert_eq!(times[2].to_string(), "25:30:05");
+    assert!(times[2].is_next_day());
+    assert!("12:60:00".parse::<ServiceTime>().is_err());
+
+    let date = NaiveDate::from_ymd_opt(2023, 3, 14).unwrap();
+    let offset = FixedOffset::west_opt(7 * 3600).unwrap();
+    assert_eq!(
+        times[2].on(date, &offset),
+        offset.with_ymd_and_hms(2023, 3, 15, 1, 30, 5).single()
+    );
+}
@@ -0,0 +1,95 @@
+use chrono::{FixedOffset, LocalResult, NaiveDate, NaiveDateTime, TimeZone, Timelike, Utc};
+use transitworld::data::ServiceTime;
+
+/// US Pacific time in 2023, when daylight saving time ran from 2:00 on March
+/// 12 until 2:00 on November 5.
+#[derive(Debug, Clone, Copy)]
+struct Pacific2023;
+
+fn pst() -> FixedOffset {
+    FixedOffset::west_opt(8 * 3600).unwrap()
+}
+
+fn pdt() -> FixedOffset {
+    FixedOffset::west_opt(7 * 3600).unwrap()
+}
+
+fn utc(month: u32, day: u32, hour: u32) -> NaiveDateTime {
+    NaiveDate::from_ymd_opt(2023, month, day)
+        .unwrap()
+        .and_hms_opt(hour, 0, 0)
+        .unwrap()
+}
+
+impl TimeZone for Pacific2023 {
+    type Offset = FixedOffset;
+
+    fn from_offset(_offset: &FixedOffset) -> Self {
+        Pacific2023
+    }
+
+    fn offset_from_local_date(&self, local: &NaiveDate) -> LocalResult<FixedOffset> {
+        self.offset_from_local_datetime(&local.and_hms_opt(0, 0, 0).unwrap())
+    }
+
+    fn offset_from_local_datetime(&self, local: &NaiveDateTime) -> LocalResult<FixedOffset> {
+        // a local time is valid with each offset in effect at its UTC time
+        let offsets: Vec<_> = [pdt(), pst()]
+            .into_iter()
+            .filter(|offset| self.offset_from_utc_datetime(&(*local - *offset)) == *offset)
+            .collect();
+        match offsets[..] {
+            [offset] => LocalResult::Single(offset),
+            [earliest, latest] => LocalResult::Ambiguous(earliest, latest),
+            _ => LocalResult::None,
+        }
+    }
+
+    fn offset_from_utc_date(&self, utc: &NaiveDate) -> FixedOffset {
+        self.offset_from_utc_datetime(&utc.and_hms_opt(0, 0, 0).unwrap())
+    }
+
+    fn offset_from_utc_datetime(&self, utc_time: &NaiveDateTime) -> FixedOffset {
+        if (utc(3, 12, 10)..utc(11, 5, 9)).contains(utc_time) {
+            pdt()
+        } else {
+            pst()
+        }
+    }
+}
+
+fn at(time: &str, month: u32, day: u32) -> NaiveDateTime {
+    let date = NaiveDate::from_ymd_opt(2023, month, day).unwrap();
+    let time: ServiceTime = time.parse().unwrap();
+    time.on(date, &Pacific2023).unwrap().naive_utc()
+}
+
+#[test]
+fn fixed_offset() {
+    let date = NaiveDate::from_ymd_opt(2023, 3, 12).unwrap();
+    let time: ServiceTime = "25:30:00".parse().unwrap();
+    let instant = time.on(date, &pst()).unwrap();
+    assert_eq!(instant.naive_local().hour(), 1);
+    assert_eq!(
+        instant,
+        Utc.with_ymd_and_hms(2023, 3, 13, 9, 30, 0).unwrap()
+    );
+}
+
+#[test]
+fn dst_start() {
+    // Daylight saving time began at 2:00, so the service day began at 23:00
+    // on the previous day.
+    assert_eq!(at("00:00:00", 3, 12), utc(3, 12, 7));
+    assert_eq!(at("01:00:00", 3, 12), utc(3, 12, 8));
+    assert_eq!(at("08:00:00", 3, 12), utc(3, 12, 15));
+    assert_eq!(at("25:00:00", 3, 12), utc(3, 13, 8));
+}
+
+#[test]
+fn dst_end() {
+    // Daylight saving time ended at 2:00, so the service day began at 1:00.
+    assert_eq!(at("00:00:00", 11, 5), utc(11, 5, 8));
+    assert_eq!(at("08:00:00", 11, 5), utc(11, 5, 16));
+    assert_eq!(at("24:00:00", 11, 5), utc(11, 6, 8));
+}
@@ -0,0 +1,82 @@
+#![cfg(feature = "chrono-tz")]
+
+use chrono::{NaiveDate, TimeZone, Utc};
+use serde_json::json;
+use transitworld::{
+    data::{partial, resolve_timezone, FeedState, ServiceTime, Tz},
+    Error,
+};
+
+#[test]
+fn stop_timezone_overrides_agency() {
+    let resolve = |stop, agency| resolve_timezone(stop, agency).unwrap();
+    assert_eq!(
+        resolve(Some("America/Chicago"), Some("America/New_York")),
+        Some(Tz::America__Chicago)
+    );
+    assert_eq!(
+        resolve(Some(""), Some("America/New_York")),
+        Some(Tz::America__New_York)
+    );
+    assert_eq!(resolve(None, None), None);
+    assert!(matches!(
+        resolve_timezone(Some("Mars/Olympus_Mons"), None),
+        Err(Error::UnknownTimezone(_))
+    ));
+}
+
+#[test]
+fn service_time_across_dst() {
+    // Daylight saving time began at 2:00 on this date, so the service day
+    // began at 23:00 on the previous day.
+    let date = NaiveDate::from_ymd_opt(2023, 3, 12).unwrap();
+    let at = |time: &str| {
+        let time: ServiceTime = time.parse().unwrap();
+        time.on(date, &Tz::America__Los_Angeles).unwrap()
+    };
+    assert_eq!(
+        at("01:00:00"),
+        Utc.with_ymd_and_hms(2023, 3, 12, 8, 0, 0).unwrap()
+    );
+    assert_eq!(
+        at("08:00:00"),
+        Utc.with_ymd_and_hms(2023, 3, 12, 15, 0, 0).unwrap()
+    );
+    assert_eq!(
+        at("25:00:00"),
+        Utc.with_ymd_and_hms(2023, 3, 13, 8, 0, 0).unwrap()
+    );
+}
+
+#[test]
+fn feed_state_utc() {
+    let state: FeedState = serde_json::from_value(json!({
+        "last_fetch_error": "",
+        "last_fetched_at": "2023-01-02T03:04:05",
+        "last_successful_fetch_at": null,
+        "feed_version": null
+    }))
+    .unwrap();
+    assert_eq!(
+        state.last_fetched_at_utc(),
+        Utc.with_ymd_and_hms(2023, 1, 2, 3, 4, 5).single()
+    );
+    assert_eq!(state.last_successful_fetch_at_utc(), None);
+}
+
+#[test]
+fn feed_version_utc() {
+    let fetched_at = |time: &str| {
+        let feed_version: partial::FeedVersion = serde_json::from_value(json!({
+            "sha1": "e535eb2b3b8e2bcd0ac6d1e5d6f95e6d0bd5d9f0",
+            "fetched_at": time
+        }))
+        .unwrap();
+        feed_version.fetched_at_utc()
+    };
+    let expected = Utc.with_ymd_and_hms(2023, 1, 2, 3, 4, 5).single();
+    assert_eq!(fetched_at("2023-01-02T03:04:05"), expected);
+    assert_eq!(fetched_at("2023-01-02T03:04:05Z"), expected);
+    assert_eq!(fetched_at("2023-01-01T19:04:05-08:00"), expected);
+    assert_eq!(fetched_at("yesterday"), None);
+}